@@ -0,0 +1,405 @@
+//! Chromium 书签格式
+//!
+//! 读写 Chrome、Edge、Brave、Vivaldi 等浏览器配置目录中的 `Bookmarks` JSON 文件。
+//! Chromium 把图标保存在单独的 `Favicons` 数据库中，`Bookmarks` 文件本身不包含图标，
+//! 因此写回 JSON 时获取到的 favicon 只保留在缓存中，需要图标时应导出为 Netscape HTML
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppResult;
+use super::model::{Bookmark, BookmarkNode, BookmarkTree, Folder};
+
+/// 书签栏根节点的固定 GUID
+const BOOKMARK_BAR_GUID: &str = "0bc5d13f-2cba-5d74-951f-3f233fe6c908";
+/// 其他书签根节点的固定 GUID
+const OTHER_GUID: &str = "82b081ec-3dd3-529c-8475-ab6c344590dd";
+/// 移动设备书签根节点的固定 GUID
+const MOBILE_GUID: &str = "4cf2e351-0e85-532b-bb37-df045d8f8d0f";
+
+/// 1601-01-01 与 1970-01-01 之间相差的秒数，Chromium 时间戳以前者为起点、以微秒为单位
+const WEBKIT_EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
+
+/// 书签模型的时间精确到秒，解析时原始的微秒时间戳以这两个键保存在 `extra_fields` 中，
+/// 写回时若时间没有被修改则原样使用，避免 Chromium 因精度丢失认为所有节点都已修改
+const DATE_ADDED: &str = "date_added";
+const DATE_MODIFIED: &str = "date_modified";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChromiumFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    roots: ChromiumRoots,
+    version: u32,
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChromiumRoots {
+    bookmark_bar: ChromiumNode,
+    other: ChromiumNode,
+    synced: ChromiumNode,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChromiumNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<ChromiumNode>>,
+    #[serde(default)]
+    date_added: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    guid: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    node_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// `meta_info`、`date_last_used` 等未建模的字段
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 判断内容是否为 Chromium 书签 JSON
+pub fn is_chromium(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|value| value.get("roots").and_then(|roots| roots.get("bookmark_bar")).is_some())
+        .unwrap_or(false)
+}
+
+/// 解析 Chromium 书签 JSON
+///
+/// 书签栏和移动设备书签映射为顶层文件夹，其他书签的内容直接放在顶层，
+/// 与 Chromium 导出 Netscape HTML 时的结构一致
+pub fn parse(content: &str) -> AppResult<BookmarkTree> {
+    let file: ChromiumFile = serde_json::from_str(content)?;
+    let mut tree = BookmarkTree::new("Bookmarks");
+    tree.extra_fields = file.extra;
+
+    let mut bookmark_bar = convert_folder(file.roots.bookmark_bar);
+    bookmark_bar.personal_toolbar_folder = true;
+    bookmark_bar.guid = Some(BOOKMARK_BAR_GUID.to_string());
+    tree.root.children.push(BookmarkNode::Folder(bookmark_bar));
+
+    let other = convert_folder(file.roots.other);
+    tree.root.children.extend(other.children);
+
+    let mut mobile = convert_folder(file.roots.synced);
+    if !mobile.children.is_empty() {
+        mobile.guid = Some(MOBILE_GUID.to_string());
+        tree.root.children.push(BookmarkNode::Folder(mobile));
+    }
+
+    Ok(tree)
+}
+
+fn convert_node(node: ChromiumNode) -> BookmarkNode {
+    if node.node_type == "url" {
+        let mut bookmark = Bookmark::new(node.name, node.url.unwrap_or_default());
+        bookmark.add_date = from_webkit_time(&node.date_added);
+        bookmark.last_modified = node.date_modified.as_deref().and_then(from_webkit_time);
+        bookmark.guid = non_empty(node.guid);
+        bookmark.id = non_empty(node.id);
+        bookmark.extra_fields = with_raw_dates(node.extra, node.date_added, node.date_modified);
+        BookmarkNode::Bookmark(bookmark)
+    } else {
+        BookmarkNode::Folder(convert_folder(node))
+    }
+}
+
+fn convert_folder(node: ChromiumNode) -> Folder {
+    let mut folder = Folder::new(node.name);
+    folder.add_date = from_webkit_time(&node.date_added);
+    folder.last_modified = node.date_modified.as_deref().and_then(from_webkit_time);
+    folder.guid = non_empty(node.guid);
+    folder.id = non_empty(node.id);
+    folder.extra_fields = with_raw_dates(node.extra, node.date_added, node.date_modified);
+    folder.children = node.children.unwrap_or_default().into_iter().map(convert_node).collect();
+    folder
+}
+
+/// 将书签树生成为 Chromium 书签 JSON
+///
+/// 第一个书签栏文件夹写入 `bookmark_bar`，带有移动设备书签 GUID 的顶层文件夹写入 `synced`，
+/// 其余顶层节点写入 `other`。解析时读到的节点 ID 和未识别字段原样写回，
+/// 新节点或 ID 重复的节点按顺序分配新 ID，并重新计算校验和
+pub fn to_json(tree: &BookmarkTree) -> AppResult<String> {
+    let mut bookmark_bar: Option<&Folder> = None;
+    let mut mobile: Option<&Folder> = None;
+    let mut other = Vec::new();
+    for node in &tree.root.children {
+        match node {
+            BookmarkNode::Folder(folder) if bookmark_bar.is_none() && folder.personal_toolbar_folder => bookmark_bar = Some(folder),
+            BookmarkNode::Folder(folder) if mobile.is_none() && folder.guid.as_deref() == Some(MOBILE_GUID) => mobile = Some(folder),
+            _ => other.push(node),
+        }
+    }
+
+    // 新 ID 从已有的最大 ID 之后开始分配，避免与保留的 ID 冲突
+    let max_id = tree.root.iter()
+        .filter_map(|node| match node {
+            BookmarkNode::Bookmark(bookmark) => bookmark.id.as_deref(),
+            BookmarkNode::Folder(folder) => folder.id.as_deref(),
+            BookmarkNode::Separator => None,
+        })
+        .filter_map(|id| id.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    let mut encoder = Encoder {
+        next_id: (max_id + 1).max(4),
+        used_ids: ["1", "2", "3"].iter().map(|id| id.to_string()).collect(),
+        checksum: md5::Context::new(),
+    };
+    let bookmark_bar = encoder.encode_root("1", bookmark_bar.map(|f| f.title.as_str()).unwrap_or("Bookmarks bar"), BOOKMARK_BAR_GUID, bookmark_bar, bookmark_bar.map(|f| f.children.iter().collect()).unwrap_or_default());
+    let other = encoder.encode_root("2", "Other bookmarks", OTHER_GUID, None, other);
+    let synced = encoder.encode_root("3", mobile.map(|f| f.title.as_str()).unwrap_or("Mobile bookmarks"), MOBILE_GUID, mobile, mobile.map(|f| f.children.iter().collect()).unwrap_or_default());
+
+    let file = ChromiumFile {
+        checksum: Some(format!("{:x}", encoder.checksum.compute())),
+        roots: ChromiumRoots { bookmark_bar, other, synced },
+        version: 1,
+        extra: tree.extra_fields.clone(),
+    };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+/// 按 Chromium 的编码顺序分配 ID 并计算 MD5 校验和
+struct Encoder {
+    next_id: u64,
+    used_ids: HashSet<String>,
+    checksum: md5::Context,
+}
+
+impl Encoder {
+    /// 优先沿用节点原有的 ID，缺失或已被占用时分配新 ID
+    fn assign_id(&mut self, original: Option<&str>) -> String {
+        if let Some(id) = original {
+            if self.used_ids.insert(id.to_string()) {
+                return id.to_string();
+            }
+        }
+        loop {
+            let id = self.next_id.to_string();
+            self.next_id += 1;
+            if self.used_ids.insert(id.clone()) {
+                return id;
+            }
+        }
+    }
+
+    fn encode_root(&mut self, id: &str, title: &str, guid: &str, folder: Option<&Folder>, children: Vec<&BookmarkNode>) -> ChromiumNode {
+        self.update_folder_checksum(id, title);
+        let mut extra = folder.map(|f| f.extra_fields.clone()).unwrap_or_default();
+        ChromiumNode {
+            children: Some(children.into_iter()
+                .filter(|child| !matches!(child, BookmarkNode::Separator))
+                .map(|child| self.encode_node(child))
+                .collect()),
+            date_added: raw_or_webkit_time(&mut extra, DATE_ADDED, folder.and_then(|f| f.add_date)),
+            date_modified: Some(raw_or_webkit_time(&mut extra, DATE_MODIFIED, folder.and_then(|f| f.last_modified))),
+            guid: guid.to_string(),
+            id: id.to_string(),
+            name: title.to_string(),
+            node_type: "folder".to_string(),
+            url: None,
+            extra,
+        }
+    }
+
+    fn encode_node(&mut self, node: &BookmarkNode) -> ChromiumNode {
+        match node {
+            BookmarkNode::Bookmark(bookmark) => {
+                let id = self.assign_id(bookmark.id.as_deref());
+                self.checksum.consume(id.as_bytes());
+                self.consume_utf16(&bookmark.title);
+                self.checksum.consume(b"url");
+                self.checksum.consume(bookmark.url.as_bytes());
+                let mut extra = bookmark.extra_fields.clone();
+                let date_added = raw_or_webkit_time(&mut extra, DATE_ADDED, bookmark.add_date);
+                let date_modified = raw_or_webkit_time(&mut extra, DATE_MODIFIED, bookmark.last_modified);
+                ChromiumNode {
+                    date_added,
+                    date_modified: bookmark.last_modified.map(|_| date_modified),
+                    guid: bookmark.guid.clone().unwrap_or_default(),
+                    id,
+                    name: bookmark.title.clone(),
+                    node_type: "url".to_string(),
+                    url: Some(bookmark.url.clone()),
+                    extra,
+                    ..Default::default()
+                }
+            }
+            BookmarkNode::Folder(folder) => {
+                let id = self.assign_id(folder.id.as_deref());
+                self.update_folder_checksum(&id, &folder.title);
+                let mut extra = folder.extra_fields.clone();
+                ChromiumNode {
+                    children: Some(folder.children.iter()
+                        .filter(|child| !matches!(child, BookmarkNode::Separator))
+                        .map(|child| self.encode_node(child))
+                        .collect()),
+                    date_added: raw_or_webkit_time(&mut extra, DATE_ADDED, folder.add_date),
+                    date_modified: Some(raw_or_webkit_time(&mut extra, DATE_MODIFIED, folder.last_modified)),
+                    guid: folder.guid.clone().unwrap_or_default(),
+                    id,
+                    name: folder.title.clone(),
+                    node_type: "folder".to_string(),
+                    url: None,
+                    extra,
+                }
+            }
+            // Chromium 没有分隔符，调用方已将其过滤
+            BookmarkNode::Separator => unreachable!("separators are filtered before encoding"),
+        }
+    }
+
+    fn update_folder_checksum(&mut self, id: &str, title: &str) {
+        self.checksum.consume(id.as_bytes());
+        self.consume_utf16(title);
+        self.checksum.consume(b"folder");
+    }
+
+    /// Chromium 对标题按 UTF-16LE 字节计算校验和
+    fn consume_utf16(&mut self, text: &str) {
+        let bytes: Vec<u8> = text.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+        self.checksum.consume(&bytes);
+    }
+}
+
+fn from_webkit_time(value: &str) -> Option<i64> {
+    let micros: i64 = value.trim().parse().ok()?;
+    if micros <= 0 {
+        return None;
+    }
+    Some(micros / 1_000_000 - WEBKIT_EPOCH_OFFSET_SECS)
+}
+
+fn to_webkit_time(seconds: Option<i64>) -> String {
+    seconds
+        .map(|s| (s + WEBKIT_EPOCH_OFFSET_SECS) * 1_000_000)
+        .unwrap_or(0)
+        .to_string()
+}
+
+/// 把原始的微秒时间戳保存到未识别字段中
+fn with_raw_dates(
+    mut extra: serde_json::Map<String, serde_json::Value>,
+    date_added: String,
+    date_modified: Option<String>,
+) -> serde_json::Map<String, serde_json::Value> {
+    extra.insert(DATE_ADDED.to_string(), serde_json::Value::String(date_added));
+    if let Some(date_modified) = date_modified {
+        extra.insert(DATE_MODIFIED.to_string(), serde_json::Value::String(date_modified));
+    }
+    extra
+}
+
+/// 从未识别字段中取出原始时间戳，与模型中的时间一致时原样返回，否则按模型中的时间重新生成
+fn raw_or_webkit_time(
+    extra: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    seconds: Option<i64>,
+) -> String {
+    match extra.remove(key) {
+        Some(serde_json::Value::String(raw)) if from_webkit_time(&raw) == seconds => raw,
+        _ => to_webkit_time(seconds),
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "checksum": "00000000000000000000000000000000",
+        "roots": {
+            "bookmark_bar": {
+                "children": [
+                    {
+                        "date_added": "13300000000000000",
+                        "date_last_used": "13310000000000000",
+                        "date_modified": "13305000000000000",
+                        "guid": "11111111-1111-4111-8111-111111111111",
+                        "id": "7",
+                        "meta_info": { "power_bookmark_meta": "abc" },
+                        "name": "Example",
+                        "type": "url",
+                        "url": "https://example.com/"
+                    }
+                ],
+                "date_added": "13300000000000000",
+                "date_modified": "0",
+                "guid": "0bc5d13f-2cba-5d74-951f-3f233fe6c908",
+                "id": "1",
+                "name": "Bookmarks bar",
+                "type": "folder"
+            },
+            "other": { "children": [], "date_added": "0", "id": "2", "name": "Other bookmarks", "type": "folder" },
+            "synced": { "children": [], "date_added": "0", "id": "3", "name": "Mobile bookmarks", "type": "folder" }
+        },
+        "sync_metadata": "c3luYw==",
+        "version": 1
+    }"#;
+
+    #[test]
+    fn round_trip_keeps_ids_and_unknown_fields() {
+        let mut tree = parse(SAMPLE).unwrap();
+        let bar = tree.root.children[0].as_folder_mut().unwrap();
+        bar.children.push(BookmarkNode::Bookmark(Bookmark::new("New", "https://new.example/")));
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&tree).unwrap()).unwrap();
+        assert_eq!(json["sync_metadata"], "c3luYw==");
+        let children = json["roots"]["bookmark_bar"]["children"].as_array().unwrap();
+        assert_eq!(children[0]["id"], "7");
+        assert_eq!(children[0]["meta_info"]["power_bookmark_meta"], "abc");
+        assert_eq!(children[0]["date_last_used"], "13310000000000000");
+        assert_eq!(children[0]["date_modified"], "13305000000000000");
+        assert_eq!(children[1]["id"], "8");
+        assert!(children[1].get("meta_info").is_none());
+    }
+
+    #[test]
+    fn unchanged_dates_keep_microsecond_precision() {
+        let sample = SAMPLE.replacen("13300000000000000", "13300000000123456", 1);
+        let mut tree = parse(&sample).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&to_json(&tree).unwrap()).unwrap();
+        let example = &json["roots"]["bookmark_bar"]["children"][0];
+        assert_eq!(example["date_added"], "13300000000123456");
+        assert_eq!(example["date_modified"], "13305000000000000");
+        assert_eq!(json["roots"]["bookmark_bar"]["date_modified"], "0");
+        assert_eq!(parse(&to_json(&tree).unwrap()).unwrap(), tree);
+
+        // 修改过的时间按秒重新生成
+        let bookmark = tree.root.children[0].as_folder_mut().unwrap().children[0].as_bookmark_mut().unwrap();
+        bookmark.add_date = bookmark.add_date.map(|seconds| seconds + 1);
+        let json: serde_json::Value = serde_json::from_str(&to_json(&tree).unwrap()).unwrap();
+        assert_eq!(json["roots"]["bookmark_bar"]["children"][0]["date_added"], "13300000001000000");
+    }
+
+    #[test]
+    fn duplicate_ids_are_reassigned() {
+        let mut tree = parse(SAMPLE).unwrap();
+        let bar = tree.root.children[0].as_folder_mut().unwrap();
+        let copy = bar.children[0].clone();
+        bar.children.push(copy);
+
+        let json: serde_json::Value = serde_json::from_str(&to_json(&tree).unwrap()).unwrap();
+        let children = json["roots"]["bookmark_bar"]["children"].as_array().unwrap();
+        assert_eq!(children[0]["id"], "7");
+        assert_eq!(children[1]["id"], "8");
+    }
+}
@@ -0,0 +1,547 @@
+//! Firefox 书签数据库
+//!
+//! 从复制出来的 Firefox 配置目录读取 `places.sqlite` 中的书签，
+//! 以及同目录 `favicons.sqlite` 中 Firefox 已保存的图标。
+//! 也可以把获取到的图标写入 `favicons.sqlite` 的副本，替换原文件后 Firefox 即可直接使用
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use crate::errors::{AppError, AppResult};
+use crate::utils::data_url;
+use super::model::{Bookmark, BookmarkNode, BookmarkTree, Folder};
+
+pub(super) const MENU_GUID: &str = "menu________";
+pub(super) const TOOLBAR_GUID: &str = "toolbar_____";
+const TAGS_GUID: &str = "tags________";
+pub(super) const UNFILED_GUID: &str = "unfiled_____";
+pub(super) const MOBILE_GUID: &str = "mobile______";
+
+pub(super) const TYPE_BOOKMARK: i64 = 1;
+pub(super) const TYPE_FOLDER: i64 = 2;
+pub(super) const TYPE_SEPARATOR: i64 = 3;
+
+/// Firefox 为没有原始地址的图标使用的伪 URL 前缀
+const FAKE_ICON_URI_PREFIX: &str = "fake-favicon-uri:";
+/// 选择图标时优先的尺寸
+const PREFERRED_ICON_WIDTH: i64 = 32;
+/// 写入的图标在 Firefox 中的有效期
+const ICON_EXPIRE_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+/// `moz_bookmarks` 中的一行
+struct Row {
+    id: i64,
+    node_type: i64,
+    title: String,
+    date_added: Option<i64>,
+    last_modified: Option<i64>,
+    guid: String,
+    url: Option<String>,
+    keyword: Option<String>,
+}
+
+/// 从 `favicons.sqlite` 读出的图标
+struct StoredIcon {
+    icon_url: String,
+    width: i64,
+    data: Vec<u8>,
+}
+
+/// 判断文件是否为 Firefox `places.sqlite`
+pub fn is_places(path: &Path) -> bool {
+    open_read_only(path)
+        .and_then(|conn| {
+            conn.query_row("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = 'moz_bookmarks'", [], |row| row.get::<_, i64>(0))
+                .map_err(AppError::from)
+        })
+        .map(|count| count > 0)
+        .unwrap_or(false)
+}
+
+/// 与 `places.sqlite` 同目录的 `favicons.sqlite` 路径
+pub fn favicons_path(places: &Path) -> PathBuf {
+    places.with_file_name("favicons.sqlite")
+}
+
+fn open_read_only(path: &Path) -> AppResult<Connection> {
+    Ok(Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?)
+}
+
+/// 读取 `places.sqlite` 中的书签
+///
+/// 书签菜单的内容放在顶层，书签工具栏、其他书签和移动设备书签作为顶层文件夹，
+/// 与 Firefox 导出 Netscape HTML 时的结构一致。同目录存在 `favicons.sqlite` 时，
+/// 书签会带上 Firefox 已保存的图标
+pub fn read(places: &Path) -> AppResult<BookmarkTree> {
+    let conn = open_read_only(places)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.type, b.parent, b.title, b.dateAdded, b.lastModified, b.guid, p.url,
+                (SELECT k.keyword FROM moz_keywords k WHERE k.place_id = b.fk LIMIT 1)
+         FROM moz_bookmarks b
+         LEFT JOIN moz_places p ON p.id = b.fk
+         ORDER BY b.parent, b.position")?;
+    let mut children: HashMap<i64, Vec<Row>> = HashMap::new();
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(2)?, Row {
+            id: row.get(0)?,
+            node_type: row.get(1)?,
+            title: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            date_added: row.get::<_, Option<i64>>(4)?.and_then(from_prtime),
+            last_modified: row.get::<_, Option<i64>>(5)?.and_then(from_prtime),
+            guid: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+            url: row.get(7)?,
+            keyword: row.get(8)?,
+        }))
+    })?;
+    for row in rows {
+        let (parent, row) = row?;
+        children.entry(parent).or_default().push(row);
+    }
+
+    let root_id = |guid: &str| -> AppResult<Option<i64>> {
+        Ok(conn.query_row("SELECT id FROM moz_bookmarks WHERE guid = ?1", params![guid], |row| row.get(0)).optional()?)
+    };
+
+    // 标签在 Firefox 中是标签根目录下的文件夹，其中的书签指向被标记的地址
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    if let Some(tags_id) = root_id(TAGS_GUID)? {
+        for tag in children.get(&tags_id).into_iter().flatten() {
+            for tagged in children.get(&tag.id).into_iter().flatten() {
+                if let Some(url) = &tagged.url {
+                    tags.entry(url.clone()).or_default().push(tag.title.clone());
+                }
+            }
+        }
+    }
+
+    let icons = match favicons_path(places) {
+        path if path.exists() => read_icons(&path)?,
+        _ => Icons::default(),
+    };
+
+    let builder = TreeBuilder { children: &children, tags: &tags, icons: &icons };
+    let mut tree = BookmarkTree::new("Bookmarks Menu");
+    if let Some(menu_id) = root_id(MENU_GUID)? {
+        tree.root.children = builder.build_children(menu_id);
+    }
+    if let Some(toolbar_id) = root_id(TOOLBAR_GUID)? {
+        let mut toolbar = builder.build_root(toolbar_id, "Bookmarks Toolbar", TOOLBAR_GUID);
+        toolbar.personal_toolbar_folder = true;
+        tree.root.children.push(BookmarkNode::Folder(toolbar));
+    }
+    if let Some(unfiled_id) = root_id(UNFILED_GUID)? {
+        let mut unfiled = builder.build_root(unfiled_id, "Other Bookmarks", UNFILED_GUID);
+        unfiled.extra_attributes.push(("UNFILED_BOOKMARKS_FOLDER".to_string(), "true".to_string()));
+        if !unfiled.children.is_empty() {
+            tree.root.children.push(BookmarkNode::Folder(unfiled));
+        }
+    }
+    if let Some(mobile_id) = root_id(MOBILE_GUID)? {
+        let mobile = builder.build_root(mobile_id, "Mobile Bookmarks", MOBILE_GUID);
+        if !mobile.children.is_empty() {
+            tree.root.children.push(BookmarkNode::Folder(mobile));
+        }
+    }
+    Ok(tree)
+}
+
+struct TreeBuilder<'a> {
+    children: &'a HashMap<i64, Vec<Row>>,
+    tags: &'a HashMap<String, Vec<String>>,
+    icons: &'a Icons,
+}
+
+impl TreeBuilder<'_> {
+    fn build_root(&self, id: i64, title: &str, guid: &str) -> Folder {
+        let mut folder = Folder::new(title);
+        folder.guid = Some(guid.to_string());
+        folder.children = self.build_children(id);
+        folder
+    }
+
+    fn build_children(&self, parent: i64) -> Vec<BookmarkNode> {
+        let mut nodes = Vec::new();
+        for row in self.children.get(&parent).into_iter().flatten() {
+            match row.node_type {
+                TYPE_BOOKMARK => {
+                    let url = row.url.clone().unwrap_or_default();
+                    let mut bookmark = Bookmark::new(row.title.clone(), url.clone());
+                    bookmark.add_date = row.date_added;
+                    bookmark.last_modified = row.last_modified;
+                    bookmark.guid = Some(row.guid.clone());
+                    bookmark.shortcut_url = row.keyword.clone();
+                    bookmark.tags = self.tags.get(&url).cloned().unwrap_or_default();
+                    if let Some(icon) = self.icons.for_page(&url) {
+                        if !icon.icon_url.starts_with(FAKE_ICON_URI_PREFIX) {
+                            bookmark.icon_uri = Some(icon.icon_url.clone());
+                        }
+                        bookmark.icon = Some(data_url::encode(sniff_mime(&icon.data), &icon.data));
+                    }
+                    nodes.push(BookmarkNode::Bookmark(bookmark));
+                }
+                TYPE_FOLDER => {
+                    let mut folder = Folder::new(row.title.clone());
+                    folder.add_date = row.date_added;
+                    folder.last_modified = row.last_modified;
+                    folder.guid = Some(row.guid.clone());
+                    folder.children = self.build_children(row.id);
+                    nodes.push(BookmarkNode::Folder(folder));
+                }
+                TYPE_SEPARATOR => nodes.push(BookmarkNode::Separator),
+                _ => {}
+            }
+        }
+        nodes
+    }
+}
+
+/// `favicons.sqlite` 中的图标，按页面地址和站点根图标分别索引
+#[derive(Default)]
+struct Icons {
+    pages: HashMap<String, StoredIcon>,
+    roots: HashMap<String, StoredIcon>,
+}
+
+impl Icons {
+    fn for_page(&self, url: &str) -> Option<&StoredIcon> {
+        self.pages.get(url).or_else(|| {
+            let host = url::Url::parse(url).ok()?.host_str()?.to_string();
+            self.roots.get(&host)
+        })
+    }
+
+    fn insert(map: &mut HashMap<String, StoredIcon>, key: String, icon: StoredIcon) {
+        let better = match map.get(&key) {
+            Some(current) => is_better_width(icon.width, current.width),
+            None => true,
+        };
+        if better {
+            map.insert(key, icon);
+        }
+    }
+}
+
+/// 优先选择不小于首选尺寸的最小图标，都小于首选尺寸时选择最大的
+fn is_better_width(candidate: i64, current: i64) -> bool {
+    match (candidate >= PREFERRED_ICON_WIDTH, current >= PREFERRED_ICON_WIDTH) {
+        (true, true) => candidate < current,
+        (true, false) => true,
+        (false, true) => false,
+        (false, false) => candidate > current,
+    }
+}
+
+fn read_icons(path: &Path) -> AppResult<Icons> {
+    let conn = open_read_only(path)?;
+    let mut icons = Icons::default();
+
+    let mut stmt = conn.prepare(
+        "SELECT p.page_url, i.icon_url, i.width, i.data
+         FROM moz_pages_w_icons p
+         JOIN moz_icons_to_pages ip ON ip.page_id = p.id
+         JOIN moz_icons i ON i.id = ip.icon_id
+         WHERE i.data IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, StoredIcon { icon_url: row.get(1)?, width: row.get(2)?, data: row.get(3)? }))
+    })?;
+    for row in rows {
+        let (page_url, icon) = row?;
+        Icons::insert(&mut icons.pages, page_url, icon);
+    }
+
+    // 站点根目录下的 favicon.ico 不与页面关联，而是作用于整个站点
+    let mut stmt = conn.prepare("SELECT icon_url, width, data FROM moz_icons WHERE root = 1 AND data IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(StoredIcon { icon_url: row.get(0)?, width: row.get(1)?, data: row.get(2)? })
+    })?;
+    for row in rows {
+        let icon = row?;
+        let host = url::Url::parse(&icon.icon_url).ok().and_then(|u| u.host_str().map(|h| h.to_string()));
+        if let Some(host) = host {
+            Icons::insert(&mut icons.roots, host, icon);
+        }
+    }
+    Ok(icons)
+}
+
+/// 把书签中的 data URL 图标写入 `favicons.sqlite` 的副本
+///
+/// 复制 `places.sqlite` 同目录的 `favicons.sqlite` 到 `output`，为尚无图标的书签页面
+/// 写入 PNG 图标。返回写入的图标数
+pub fn write_favicons(places: &Path, output: &Path, tree: &BookmarkTree) -> AppResult<usize> {
+    let source = favicons_path(places);
+    if !source.exists() {
+        return Err(AppError::FileNotFound(source.display().to_string()));
+    }
+    fs::copy(&source, output)?;
+
+    let mut conn = Connection::open(output)?;
+    let tx = conn.transaction()?;
+    let expire_ms = chrono::Utc::now().timestamp_millis() + ICON_EXPIRE_MS;
+    let mut written = 0;
+    for bookmark in tree.bookmarks() {
+        let Some((_, bytes)) = bookmark.icon.as_deref().and_then(data_url::decode) else {
+            continue;
+        };
+        let has_icon: Option<i64> = tx.query_row(
+            "SELECT p.id FROM moz_pages_w_icons p JOIN moz_icons_to_pages ip ON ip.page_id = p.id
+             WHERE p.page_url_hash = ?1 AND p.page_url = ?2 LIMIT 1",
+            params![hash_url(&bookmark.url), bookmark.url], |row| row.get(0)).optional()?;
+        if has_icon.is_some() || has_root_icon(&tx, &bookmark.url)? {
+            continue;
+        }
+        // Firefox 只保存 PNG 和 SVG，其他格式统一转换为 PNG
+        let Ok(image) = image::load_from_memory(&bytes) else {
+            continue;
+        };
+        let mut png = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+        let page_id: i64 = match tx.query_row(
+            "SELECT id FROM moz_pages_w_icons WHERE page_url_hash = ?1 AND page_url = ?2",
+            params![hash_url(&bookmark.url), bookmark.url], |row| row.get(0)).optional()? {
+            Some(id) => id,
+            None => {
+                tx.execute("INSERT INTO moz_pages_w_icons (page_url, page_url_hash) VALUES (?1, ?2)",
+                    params![bookmark.url, hash_url(&bookmark.url)])?;
+                tx.last_insert_rowid()
+            }
+        };
+        let icon_url = format!("{}{}", FAKE_ICON_URI_PREFIX, bookmark.url);
+        tx.execute(
+            "INSERT INTO moz_icons (icon_url, fixed_icon_url_hash, width, root, expire_ms, data) VALUES (?1, ?2, ?3, 0, ?4, ?5)",
+            params![icon_url, hash_url(&icon_url), image.width().min(u16::MAX as u32), expire_ms, png])?;
+        let icon_id = tx.last_insert_rowid();
+        tx.execute("INSERT OR REPLACE INTO moz_icons_to_pages (page_id, icon_id, expire_ms) VALUES (?1, ?2, ?3)",
+            params![page_id, icon_id, expire_ms])?;
+        written += 1;
+    }
+    tx.commit()?;
+    Ok(written)
+}
+
+/// 页面所在站点是否已有根目录图标（`/favicon.ico`）
+fn has_root_icon(conn: &Connection, page_url: &str) -> AppResult<bool> {
+    let Some(origin) = url::Url::parse(page_url).ok().map(|u| u.origin().ascii_serialization()) else {
+        return Ok(false);
+    };
+    let icon_url = format!("{}/favicon.ico", origin);
+    let found: Option<i64> = conn.query_row("SELECT id FROM moz_icons WHERE root = 1 AND icon_url = ?1 LIMIT 1",
+        params![icon_url], |row| row.get(0)).optional()?;
+    Ok(found.is_some())
+}
+
+/// Firefox 的 `hash()` SQL 函数，用于 `page_url_hash` 等索引列
+///
+/// 低 32 位为整个地址（最多 1500 字节）的 HashString，高 16 位为协议部分的 HashString
+fn hash_url(url: &str) -> i64 {
+    const MAX_CHARS_TO_HASH: usize = 1500;
+    let bytes = url.as_bytes();
+    let str_hash = mozilla_hash(&bytes[..bytes.len().min(MAX_CHARS_TO_HASH)]) as i64;
+    match url.find(':') {
+        Some(scheme_end) => (((mozilla_hash(&bytes[..scheme_end]) & 0xFFFF) as i64) << 32) + str_hash,
+        None => str_hash,
+    }
+}
+
+/// mozilla::HashString
+fn mozilla_hash(bytes: &[u8]) -> u32 {
+    const GOLDEN_RATIO_U32: u32 = 0x9E37_79B9;
+    bytes.iter().fold(0u32, |hash, &b| (hash.rotate_left(5) ^ b as u32).wrapping_mul(GOLDEN_RATIO_U32))
+}
+
+/// 根据文件头判断 Firefox 保存的图标类型，Firefox 只保存位图和 SVG，无法识别的按 SVG 处理
+fn sniff_mime(data: &[u8]) -> &'static str {
+    crate::utils::image::sniff_mime(data).unwrap_or("image/svg+xml")
+}
+
+/// PRTime（微秒）转换为 Unix 秒
+pub(super) fn from_prtime(micros: i64) -> Option<i64> {
+    if micros <= 0 {
+        None
+    } else {
+        Some(micros / 1_000_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bookmarks::BookmarkFormat;
+    use crate::favicon::store::{CacheStore, JsonStore};
+
+    const PLACES_SCHEMA: &str = "
+        CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT);
+        CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER, parent INTEGER, position INTEGER,
+            title TEXT, dateAdded INTEGER, lastModified INTEGER, guid TEXT);
+        CREATE TABLE moz_keywords (id INTEGER PRIMARY KEY, keyword TEXT, place_id INTEGER);";
+
+    const FAVICONS_SCHEMA: &str = "
+        CREATE TABLE moz_icons (id INTEGER PRIMARY KEY, icon_url TEXT NOT NULL, fixed_icon_url_hash INTEGER NOT NULL,
+            width INTEGER NOT NULL DEFAULT 0, root INTEGER NOT NULL DEFAULT 0, color INTEGER,
+            expire_ms INTEGER NOT NULL DEFAULT 0, data BLOB);
+        CREATE TABLE moz_pages_w_icons (id INTEGER PRIMARY KEY, page_url TEXT NOT NULL, page_url_hash INTEGER NOT NULL);
+        CREATE TABLE moz_icons_to_pages (page_id INTEGER NOT NULL, icon_id INTEGER NOT NULL,
+            expire_ms INTEGER NOT NULL DEFAULT 0, PRIMARY KEY (page_id, icon_id)) WITHOUT ROWID;";
+
+    /// 左上角像素颜色不同，避免被当作空白的占位图标
+    fn png(width: u32) -> Vec<u8> {
+        let mut image = image::RgbaImage::from_pixel(width, width, image::Rgba([200, 30, 30, 255]));
+        image.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+        let mut bytes = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    /// 菜单中有一个带标签和关键字的书签、一个子文件夹和一个分隔符，工具栏中有一个没有图标的书签
+    fn create_places(dir: &Path) -> PathBuf {
+        let path = dir.join("places.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(PLACES_SCHEMA).unwrap();
+        conn.execute_batch("
+            INSERT INTO moz_places VALUES (1, 'https://example.com/page'), (2, 'https://www.rust-lang.org/'), (3, 'https://plain.test/');
+            INSERT INTO moz_bookmarks VALUES
+                (1, 2, NULL, 0, 0, '', 0, 0, 'root________'),
+                (2, 2, NULL, 1, 0, 'menu', 0, 0, 'menu________'),
+                (3, 2, NULL, 1, 1, 'toolbar', 0, 0, 'toolbar_____'),
+                (4, 2, NULL, 1, 2, 'tags', 0, 0, 'tags________'),
+                (5, 2, NULL, 1, 3, 'unfiled', 0, 0, 'unfiled_____'),
+                (6, 2, NULL, 1, 4, 'mobile', 0, 0, 'mobile______'),
+                (10, 1, 1, 2, 0, 'Example', 1700000000000000, 1700000100000000, 'bookmark0001'),
+                (11, 2, NULL, 2, 1, 'Dev', 1700000000000000, 0, 'folder000001'),
+                (12, 1, 2, 11, 0, 'Rust', 0, 0, 'bookmark0002'),
+                (13, 3, NULL, 2, 2, NULL, 0, 0, 'separator001'),
+                (14, 1, 3, 3, 0, 'Plain', 0, 0, 'bookmark0003'),
+                (20, 2, NULL, 4, 0, 'news', 0, 0, 'tagfolder001'),
+                (21, 1, 1, 20, 0, NULL, 0, 0, 'tagentry0001');
+            INSERT INTO moz_keywords VALUES (1, 'ex', 1);").unwrap();
+        path
+    }
+
+    /// 示例页面有 16px 和 32px 两个图标，rust-lang.org 只有站点根图标
+    fn create_favicons(dir: &Path) {
+        let conn = Connection::open(dir.join("favicons.sqlite")).unwrap();
+        conn.execute_batch(FAVICONS_SCHEMA).unwrap();
+        conn.execute("INSERT INTO moz_icons (id, icon_url, fixed_icon_url_hash, width, root, data) VALUES (?1, ?2, 0, ?3, ?4, ?5)",
+            params![1, "https://example.com/icon16.png", 16, 0, png(16)]).unwrap();
+        conn.execute("INSERT INTO moz_icons (id, icon_url, fixed_icon_url_hash, width, root, data) VALUES (?1, ?2, 0, ?3, ?4, ?5)",
+            params![2, "https://example.com/icon32.png", 32, 0, png(32)]).unwrap();
+        conn.execute("INSERT INTO moz_icons (id, icon_url, fixed_icon_url_hash, width, root, data) VALUES (?1, ?2, 0, ?3, ?4, ?5)",
+            params![3, "https://www.rust-lang.org/favicon.ico", 16, 1, png(16)]).unwrap();
+        conn.execute("INSERT INTO moz_pages_w_icons VALUES (1, 'https://example.com/page', ?1)",
+            params![hash_url("https://example.com/page")]).unwrap();
+        conn.execute_batch("INSERT INTO moz_icons_to_pages VALUES (1, 1, 0), (1, 2, 0);").unwrap();
+    }
+
+    fn bookmark<'a>(tree: &'a BookmarkTree, title: &str) -> &'a Bookmark {
+        tree.bookmarks().find(|bookmark| bookmark.title == title).unwrap()
+    }
+
+    #[test]
+    fn detects_places_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = create_places(dir.path());
+        assert_eq!(BookmarkFormat::detect(&places).unwrap(), BookmarkFormat::FirefoxPlaces);
+
+        let other = dir.path().join("other.sqlite");
+        Connection::open(&other).unwrap().execute_batch("CREATE TABLE t (id INTEGER);").unwrap();
+        assert!(!is_places(&other));
+    }
+
+    #[test]
+    fn reads_tree_and_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = create_places(dir.path());
+        create_favicons(dir.path());
+        let tree = read(&places).unwrap();
+
+        // 菜单内容在顶层，工具栏为顶层文件夹，空的其他书签和移动设备书签被省略
+        let titles: Vec<&str> = tree.root.children.iter().map(BookmarkNode::title).collect();
+        assert_eq!(titles, ["Example", "Dev", "", "Bookmarks Toolbar"]);
+        assert_eq!(tree.root.children[2], BookmarkNode::Separator);
+        let toolbar = tree.root.children[3].as_folder().unwrap();
+        assert!(toolbar.personal_toolbar_folder);
+        assert_eq!(toolbar.guid.as_deref(), Some(TOOLBAR_GUID));
+
+        let example = bookmark(&tree, "Example");
+        assert_eq!(example.url, "https://example.com/page");
+        assert_eq!(example.add_date, Some(1_700_000_000));
+        assert_eq!(example.last_modified, Some(1_700_000_100));
+        assert_eq!(example.guid.as_deref(), Some("bookmark0001"));
+        assert_eq!(example.tags, ["news"]);
+        assert_eq!(example.shortcut_url.as_deref(), Some("ex"));
+        // 同一页面有多个图标时选择 32px 的
+        assert_eq!(example.icon_uri.as_deref(), Some("https://example.com/icon32.png"));
+        assert_eq!(example.icon.as_deref(), Some(data_url::encode("image/png", &png(32)).as_str()));
+
+        // 没有页面图标时使用站点根图标
+        let rust = bookmark(&tree, "Rust");
+        assert_eq!(rust.icon_uri.as_deref(), Some("https://www.rust-lang.org/favicon.ico"));
+        assert!(rust.icon.is_some());
+
+        assert!(bookmark(&tree, "Plain").icon.is_none());
+    }
+
+    #[test]
+    fn reads_without_favicons_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = create_places(dir.path());
+        let tree = read(&places).unwrap();
+        assert_eq!(tree.bookmarks().count(), 3);
+        assert!(tree.bookmarks().all(|bookmark| bookmark.icon.is_none()));
+    }
+
+    #[test]
+    fn seeds_cache_from_stored_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = create_places(dir.path());
+        create_favicons(dir.path());
+        let tree = read(&places).unwrap();
+
+        let mut store = JsonStore::open(dir.path().join("cache.json").to_string_lossy());
+        assert_eq!(store.seed_from_tree(&tree).unwrap(), 2);
+        let entry = store.get("example.com").unwrap().unwrap();
+        assert_eq!(entry.icon(), bookmark(&tree, "Example").icon.as_ref());
+        assert!(store.get("www.rust-lang.org").unwrap().is_some());
+        assert!(store.get("plain.test").unwrap().is_none());
+
+        // 已有图标的域名不会被再次填充
+        assert_eq!(store.seed_from_tree(&tree).unwrap(), 0);
+    }
+
+    #[test]
+    fn write_favicons_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let places = create_places(dir.path());
+        create_favicons(dir.path());
+        let mut tree = read(&places).unwrap();
+        let icon = data_url::encode("image/png", &png(16));
+        for bookmark in tree.root.bookmarks_mut() {
+            bookmark.icon = Some(icon.clone());
+        }
+
+        // 写入的副本放到另一个配置目录，作为该目录的 favicons.sqlite 重新读取
+        let copy = tempfile::tempdir().unwrap();
+        let copied_places = copy.path().join("places.sqlite");
+        fs::copy(&places, &copied_places).unwrap();
+        let written = write_favicons(&places, &favicons_path(&copied_places), &tree).unwrap();
+        // 已有页面图标或站点根图标的书签保持不变
+        assert_eq!(written, 1);
+
+        let reread = read(&copied_places).unwrap();
+        let plain = bookmark(&reread, "Plain");
+        assert_eq!(plain.icon.as_deref(), Some(icon.as_str()));
+        assert_eq!(plain.icon_uri, None);
+        assert_eq!(bookmark(&reread, "Example").icon_uri.as_deref(), Some("https://example.com/icon32.png"));
+
+        let conn = Connection::open(favicons_path(&copied_places)).unwrap();
+        let hash: i64 = conn.query_row("SELECT page_url_hash FROM moz_pages_w_icons WHERE page_url = 'https://plain.test/'", [], |row| row.get(0)).unwrap();
+        assert_eq!(hash, hash_url("https://plain.test/"));
+        // 原始的 favicons.sqlite 未被修改
+        assert!(bookmark(&read(&places).unwrap(), "Plain").icon.is_none());
+    }
+}
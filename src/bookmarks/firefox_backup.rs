@@ -0,0 +1,377 @@
+//! Firefox 书签备份
+//!
+//! 读写 Firefox 的 JSON 书签备份：手动备份为普通 JSON（`.json`），
+//! `bookmarkbackups/` 目录中的自动备份为 mozLz4 压缩的 JSON（`.jsonlz4`）。
+//! 生成的备份可以在 Firefox 书签管理器中通过"恢复"直接导入
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{AppError, AppResult};
+use super::firefox::{from_prtime, MENU_GUID, MOBILE_GUID, TOOLBAR_GUID, TYPE_BOOKMARK, TYPE_FOLDER, TYPE_SEPARATOR, UNFILED_GUID};
+use super::model::{Bookmark, BookmarkNode, BookmarkTree, Folder};
+
+/// mozLz4 文件头
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+/// LZ4 数据块的最大压缩比，文件头中的解压后长度超过压缩数据长度的这个倍数时必然是损坏的
+const LZ4_MAX_RATIO: usize = 255;
+/// 解压后长度的上限，防止损坏的文件头导致分配过大的内存
+const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+const ROOT_GUID: &str = "root________";
+const TYPE_CONTAINER: &str = "text/x-moz-place-container";
+const TYPE_PLACE: &str = "text/x-moz-place";
+const TYPE_PLACE_SEPARATOR: &str = "text/x-moz-place-separator";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    guid: Option<String>,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_added: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<i64>,
+    #[serde(default)]
+    id: i64,
+    #[serde(default)]
+    type_code: i64,
+    #[serde(rename = "type", default)]
+    node_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    keyword: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tags: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<BackupNode>>,
+}
+
+/// 判断数据是否为 mozLz4 压缩文件
+pub fn is_mozlz4(bytes: &[u8]) -> bool {
+    bytes.starts_with(MOZLZ4_MAGIC)
+}
+
+/// 判断内容是否为 Firefox JSON 书签备份
+pub fn is_backup(content: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(content)
+        .map(|value| {
+            value.get("root").and_then(|root| root.as_str()) == Some("placesRoot")
+                || value.get("guid").and_then(|guid| guid.as_str()) == Some(ROOT_GUID)
+        })
+        .unwrap_or(false)
+}
+
+/// 解压 mozLz4 数据
+///
+/// 格式为 8 字节文件头、4 字节小端解压后长度，以及一个 LZ4 数据块
+pub fn decompress(bytes: &[u8]) -> AppResult<Vec<u8>> {
+    if !is_mozlz4(bytes) || bytes.len() < 12 {
+        return Err(AppError::CustomError("Not a mozLz4 file".to_string()));
+    }
+    let size = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+    let block = &bytes[12..];
+    if size > MAX_DECOMPRESSED_SIZE || size > block.len().saturating_mul(LZ4_MAX_RATIO) {
+        return Err(AppError::CustomError(format!("mozLz4 decompressed size too large: {}", size)));
+    }
+    lz4_flex::block::decompress(block, size)
+        .map_err(|e| AppError::CustomError(format!("mozLz4 decompress error: {}", e)))
+}
+
+/// 压缩为 mozLz4 数据
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() / 2 + 12);
+    bytes.extend_from_slice(MOZLZ4_MAGIC);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&lz4_flex::block::compress(data));
+    bytes
+}
+
+/// 解析 Firefox JSON 书签备份
+///
+/// 书签菜单的内容放在顶层，书签工具栏、其他书签和移动设备书签作为顶层文件夹，
+/// 与读取 `places.sqlite` 时的结构一致
+pub fn parse(content: &str) -> AppResult<BookmarkTree> {
+    let root: BackupNode = serde_json::from_str(content)?;
+    let mut tree = BookmarkTree::new("Bookmarks Menu");
+    for child in root.children.unwrap_or_default() {
+        match child.guid.as_deref() {
+            Some(MENU_GUID) => tree.root.children.extend(convert_children(child.children)),
+            Some(TOOLBAR_GUID) => {
+                let mut toolbar = convert_root(child, "Bookmarks Toolbar", TOOLBAR_GUID);
+                toolbar.personal_toolbar_folder = true;
+                tree.root.children.push(BookmarkNode::Folder(toolbar));
+            }
+            Some(UNFILED_GUID) => {
+                let mut unfiled = convert_root(child, "Other Bookmarks", UNFILED_GUID);
+                unfiled.extra_attributes.push(("UNFILED_BOOKMARKS_FOLDER".to_string(), "true".to_string()));
+                if !unfiled.children.is_empty() {
+                    tree.root.children.push(BookmarkNode::Folder(unfiled));
+                }
+            }
+            Some(MOBILE_GUID) => {
+                let mobile = convert_root(child, "Mobile Bookmarks", MOBILE_GUID);
+                if !mobile.children.is_empty() {
+                    tree.root.children.push(BookmarkNode::Folder(mobile));
+                }
+            }
+            _ => tree.root.children.push(convert_node(child)),
+        }
+    }
+    Ok(tree)
+}
+
+fn convert_root(node: BackupNode, title: &str, guid: &str) -> Folder {
+    let mut folder = Folder::new(title);
+    folder.guid = Some(guid.to_string());
+    folder.add_date = node.date_added.and_then(from_prtime);
+    folder.last_modified = node.last_modified.and_then(from_prtime);
+    folder.children = convert_children(node.children);
+    folder
+}
+
+fn convert_children(children: Option<Vec<BackupNode>>) -> Vec<BookmarkNode> {
+    children.unwrap_or_default().into_iter().map(convert_node).collect()
+}
+
+fn convert_node(node: BackupNode) -> BookmarkNode {
+    match node.type_code {
+        TYPE_SEPARATOR => BookmarkNode::Separator,
+        TYPE_BOOKMARK => {
+            let mut bookmark = Bookmark::new(node.title, node.uri.unwrap_or_default());
+            bookmark.add_date = node.date_added.and_then(from_prtime);
+            bookmark.last_modified = node.last_modified.and_then(from_prtime);
+            bookmark.guid = node.guid;
+            bookmark.icon_uri = node.icon_uri;
+            bookmark.icon = node.icon;
+            bookmark.shortcut_url = node.keyword;
+            bookmark.tags = node.tags.unwrap_or_default().split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            BookmarkNode::Bookmark(bookmark)
+        }
+        _ => {
+            let mut folder = Folder::new(node.title);
+            folder.add_date = node.date_added.and_then(from_prtime);
+            folder.last_modified = node.last_modified.and_then(from_prtime);
+            folder.guid = node.guid;
+            folder.children = convert_children(node.children);
+            BookmarkNode::Folder(folder)
+        }
+    }
+}
+
+/// 将书签树生成为 Firefox JSON 书签备份
+///
+/// 书签的 data URL 图标写入 `icon` 字段，Firefox 恢复备份时会直接使用，无需重新下载
+pub fn to_json(tree: &BookmarkTree) -> AppResult<String> {
+    let mut toolbar: Option<&Folder> = None;
+    let mut unfiled: Option<&Folder> = None;
+    let mut mobile: Option<&Folder> = None;
+    let mut menu = Vec::new();
+    for node in &tree.root.children {
+        match node {
+            BookmarkNode::Folder(folder) if toolbar.is_none() && folder.personal_toolbar_folder => toolbar = Some(folder),
+            BookmarkNode::Folder(folder) if unfiled.is_none() && is_unfiled(folder) => unfiled = Some(folder),
+            BookmarkNode::Folder(folder) if mobile.is_none() && folder.guid.as_deref() == Some(MOBILE_GUID) => mobile = Some(folder),
+            _ => menu.push(node),
+        }
+    }
+
+    let mut encoder = Encoder { next_id: 7 };
+    let roots = vec![
+        encoder.encode_root(2, 0, MENU_GUID, "menu", "bookmarksMenuFolder", None, menu),
+        encoder.encode_root(3, 1, TOOLBAR_GUID, "toolbar", "toolbarFolder", toolbar, toolbar.map(|f| f.children.iter().collect()).unwrap_or_default()),
+        encoder.encode_root(5, 2, UNFILED_GUID, "unfiled", "unfiledBookmarksFolder", unfiled, unfiled.map(|f| f.children.iter().collect()).unwrap_or_default()),
+        encoder.encode_root(6, 3, MOBILE_GUID, "mobile", "mobileFolder", mobile, mobile.map(|f| f.children.iter().collect()).unwrap_or_default()),
+    ];
+    let root = BackupNode {
+        guid: Some(ROOT_GUID.to_string()),
+        id: 1,
+        type_code: TYPE_FOLDER,
+        node_type: TYPE_CONTAINER.to_string(),
+        root: Some("placesRoot".to_string()),
+        children: Some(roots),
+        ..Default::default()
+    };
+    Ok(serde_json::to_string(&root)?)
+}
+
+fn is_unfiled(folder: &Folder) -> bool {
+    folder.guid.as_deref() == Some(UNFILED_GUID)
+        || folder.extra_attributes.iter().any(|(name, value)| name == "UNFILED_BOOKMARKS_FOLDER" && value == "true")
+}
+
+struct Encoder {
+    next_id: i64,
+}
+
+impl Encoder {
+    #[allow(clippy::too_many_arguments)]
+    fn encode_root(&mut self, id: i64, index: usize, guid: &str, title: &str, root: &str, folder: Option<&Folder>, children: Vec<&BookmarkNode>) -> BackupNode {
+        BackupNode {
+            guid: Some(guid.to_string()),
+            title: title.to_string(),
+            index,
+            date_added: folder.and_then(|f| to_prtime(f.add_date)),
+            last_modified: folder.and_then(|f| to_prtime(f.last_modified)),
+            id,
+            type_code: TYPE_FOLDER,
+            node_type: TYPE_CONTAINER.to_string(),
+            root: Some(root.to_string()),
+            children: Some(children.into_iter().enumerate().map(|(i, child)| self.encode_node(child, i)).collect()),
+            ..Default::default()
+        }
+    }
+
+    fn encode_node(&mut self, node: &BookmarkNode, index: usize) -> BackupNode {
+        let id = self.next_id;
+        self.next_id += 1;
+        match node {
+            BookmarkNode::Bookmark(bookmark) => BackupNode {
+                guid: bookmark.guid.clone().filter(|guid| is_valid_guid(guid)),
+                title: bookmark.title.clone(),
+                index,
+                date_added: to_prtime(bookmark.add_date),
+                last_modified: to_prtime(bookmark.last_modified),
+                id,
+                type_code: TYPE_BOOKMARK,
+                node_type: TYPE_PLACE.to_string(),
+                uri: Some(bookmark.url.clone()),
+                icon_uri: bookmark.icon_uri.clone(),
+                icon: bookmark.icon.clone().filter(|icon| icon.starts_with("data:")),
+                keyword: bookmark.shortcut_url.clone(),
+                tags: if bookmark.tags.is_empty() { None } else { Some(bookmark.tags.join(",")) },
+                ..Default::default()
+            },
+            BookmarkNode::Folder(folder) => BackupNode {
+                guid: folder.guid.clone().filter(|guid| is_valid_guid(guid)),
+                title: folder.title.clone(),
+                index,
+                date_added: to_prtime(folder.add_date),
+                last_modified: to_prtime(folder.last_modified),
+                id,
+                type_code: TYPE_FOLDER,
+                node_type: TYPE_CONTAINER.to_string(),
+                children: Some(folder.children.iter().enumerate().map(|(i, child)| self.encode_node(child, i)).collect()),
+                ..Default::default()
+            },
+            BookmarkNode::Separator => BackupNode {
+                index,
+                id,
+                type_code: TYPE_SEPARATOR,
+                node_type: TYPE_PLACE_SEPARATOR.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Firefox 的 GUID 为 12 位 URL 安全的 base64 字符，其他浏览器的 GUID 需要由 Firefox 重新生成
+fn is_valid_guid(guid: &str) -> bool {
+    guid.len() == 12 && guid.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn to_prtime(seconds: Option<i64>) -> Option<i64> {
+    seconds.map(|s| s * 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "guid": "root________", "title": "", "index": 0, "id": 1, "typeCode": 2,
+        "type": "text/x-moz-place-container", "root": "placesRoot",
+        "children": [
+            {
+                "guid": "menu________", "title": "menu", "index": 0, "id": 2, "typeCode": 2,
+                "type": "text/x-moz-place-container", "root": "bookmarksMenuFolder",
+                "children": [
+                    {
+                        "guid": "AAAAAAAAAAAA", "title": "Example", "index": 0, "id": 7, "typeCode": 1,
+                        "type": "text/x-moz-place", "uri": "https://example.com/",
+                        "dateAdded": 1700000000000000, "lastModified": 1700000100000000,
+                        "keyword": "ex", "tags": "a,b"
+                    },
+                    { "index": 1, "id": 8, "typeCode": 3, "type": "text/x-moz-place-separator" }
+                ]
+            },
+            {
+                "guid": "toolbar_____", "title": "toolbar", "index": 1, "id": 3, "typeCode": 2,
+                "type": "text/x-moz-place-container", "root": "toolbarFolder",
+                "children": [
+                    {
+                        "guid": "BBBBBBBBBBBB", "title": "Docs", "index": 0, "id": 9, "typeCode": 2,
+                        "type": "text/x-moz-place-container",
+                        "children": [
+                            {
+                                "guid": "CCCCCCCCCCCC", "title": "Rust", "index": 0, "id": 10, "typeCode": 1,
+                                "type": "text/x-moz-place", "uri": "https://www.rust-lang.org/"
+                            }
+                        ]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn compress_round_trips() {
+        let data = SAMPLE.repeat(4).into_bytes();
+        let bytes = compress(&data);
+        assert!(is_mozlz4(&bytes));
+        assert_eq!(decompress(&bytes).unwrap(), data);
+        assert_eq!(decompress(&compress(b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn invalid_headers_are_rejected() {
+        let mut bytes = compress(SAMPLE.as_bytes());
+        assert!(decompress(&bytes[..10]).is_err());
+        assert!(decompress(MOZLZ4_MAGIC).is_err());
+
+        bytes[0] = b'x';
+        assert!(!is_mozlz4(&bytes));
+        assert!(decompress(&bytes).is_err());
+    }
+
+    #[test]
+    fn oversized_length_is_rejected_before_allocating() {
+        let mut bytes = MOZLZ4_MAGIC.to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0x10, b'a']);
+        assert!(decompress(&bytes).is_err());
+    }
+
+    #[test]
+    fn jsonlz4_round_trips() {
+        let content = decompress(&compress(SAMPLE.as_bytes())).unwrap();
+        let tree = parse(&String::from_utf8(content).unwrap()).unwrap();
+        assert_eq!(tree.root.children.len(), 3);
+        let example = tree.root.children[0].as_bookmark().unwrap();
+        assert_eq!(example.url, "https://example.com/");
+        assert_eq!(example.add_date, Some(1_700_000_000));
+        assert_eq!(example.shortcut_url.as_deref(), Some("ex"));
+        assert_eq!(example.tags, vec!["a", "b"]);
+        assert!(matches!(tree.root.children[1], BookmarkNode::Separator));
+        let toolbar = tree.root.children[2].as_folder().unwrap();
+        assert!(toolbar.personal_toolbar_folder);
+
+        let json = to_json(&tree).unwrap();
+        assert!(is_backup(&json));
+        let written = decompress(&compress(json.as_bytes())).unwrap();
+        assert_eq!(parse(&String::from_utf8(written).unwrap()).unwrap(), tree);
+    }
+}
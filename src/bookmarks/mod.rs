@@ -0,0 +1,145 @@
+//! 书签模块
+//!
+//! 提供书签树模型以及各种书签文件格式的读写
+
+pub mod model;
+pub mod netscape;
+pub mod chromium;
+pub mod firefox;
+pub mod firefox_backup;
+pub mod safari;
+
+pub use model::{Bookmark, BookmarkNode, BookmarkTree, Folder, Nodes};
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::errors::{AppError, AppResult};
+
+/// SQLite 数据库文件头
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// 书签文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkFormat {
+    /// Netscape 书签 HTML，各浏览器导出书签时使用的格式
+    Netscape,
+    /// Chromium 系浏览器配置目录中的 `Bookmarks` JSON 文件
+    Chromium,
+    /// Firefox 配置目录中的 `places.sqlite` 数据库，图标来自同目录的 `favicons.sqlite`
+    FirefoxPlaces,
+    /// Firefox 书签管理器"备份"生成的 JSON 文件
+    FirefoxBackup,
+    /// Firefox 配置目录 `bookmarkbackups/` 中 mozLz4 压缩的 JSON 备份
+    FirefoxBackupLz4,
+    /// Safari 的 `Bookmarks.plist`，只支持读取
+    Safari,
+}
+
+impl BookmarkFormat {
+    /// 根据文件内容识别书签格式，无法识别时按 Netscape HTML 处理
+    pub fn detect(path: &Path) -> AppResult<Self> {
+        // 数据库文件可能很大，先只读取文件头判断是否为 SQLite
+        let mut header = Vec::with_capacity(SQLITE_HEADER.len());
+        fs::File::open(path)?.take(SQLITE_HEADER.len() as u64).read_to_end(&mut header)?;
+        if header == SQLITE_HEADER && firefox::is_places(path) {
+            return Ok(BookmarkFormat::FirefoxPlaces);
+        }
+        let bytes = fs::read(path)?;
+        if safari::is_safari(&bytes) {
+            return Ok(BookmarkFormat::Safari);
+        }
+        if firefox_backup::is_mozlz4(&bytes) {
+            return Ok(BookmarkFormat::FirefoxBackupLz4);
+        }
+        let content = String::from_utf8_lossy(&bytes);
+        if content.trim_start().starts_with('{') {
+            if chromium::is_chromium(&content) {
+                return Ok(BookmarkFormat::Chromium);
+            }
+            if firefox_backup::is_backup(&content) {
+                return Ok(BookmarkFormat::FirefoxBackup);
+            }
+        }
+        Ok(BookmarkFormat::Netscape)
+    }
+
+    /// 格式名称，用于日志显示
+    pub fn name(self) -> &'static str {
+        match self {
+            BookmarkFormat::Netscape => "Netscape HTML",
+            BookmarkFormat::Chromium => "Chromium JSON",
+            BookmarkFormat::FirefoxPlaces => "Firefox places.sqlite",
+            BookmarkFormat::FirefoxBackup => "Firefox JSON",
+            BookmarkFormat::FirefoxBackupLz4 => "Firefox jsonlz4",
+            BookmarkFormat::Safari => "Safari Bookmarks.plist",
+        }
+    }
+
+    /// 输出文件的扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            BookmarkFormat::Netscape => "html",
+            BookmarkFormat::Chromium => "json",
+            BookmarkFormat::FirefoxPlaces => "sqlite",
+            BookmarkFormat::FirefoxBackup => "json",
+            BookmarkFormat::FirefoxBackupLz4 => "jsonlz4",
+            BookmarkFormat::Safari => "plist",
+        }
+    }
+
+    /// 是否支持以该格式输出，不支持时应输出 Netscape HTML
+    pub fn is_writable(self) -> bool {
+        !matches!(self, BookmarkFormat::Safari)
+    }
+
+    /// 读取书签文件
+    pub fn read(self, path: &Path) -> AppResult<BookmarkTree> {
+        match self {
+            BookmarkFormat::Netscape => netscape::parse(&fs::read_to_string(path)?),
+            BookmarkFormat::Chromium => chromium::parse(&fs::read_to_string(path)?),
+            BookmarkFormat::FirefoxPlaces => firefox::read(path),
+            BookmarkFormat::FirefoxBackup => firefox_backup::parse(&fs::read_to_string(path)?),
+            BookmarkFormat::FirefoxBackupLz4 => {
+                let content = firefox_backup::decompress(&fs::read(path)?)?;
+                firefox_backup::parse(&String::from_utf8_lossy(&content))
+            }
+            BookmarkFormat::Safari => safari::parse(&fs::read(path)?),
+        }
+    }
+
+    /// 写入书签文件
+    ///
+    /// `places.sqlite` 不能直接写入，应使用 [`firefox::write_favicons`] 生成 `favicons.sqlite` 副本
+    pub fn write(self, tree: &BookmarkTree, path: &Path) -> AppResult<()> {
+        let content = match self {
+            BookmarkFormat::Netscape => netscape::to_html(tree).into_bytes(),
+            BookmarkFormat::Chromium => chromium::to_json(tree)?.into_bytes(),
+            BookmarkFormat::FirefoxBackup => firefox_backup::to_json(tree)?.into_bytes(),
+            BookmarkFormat::FirefoxBackupLz4 => firefox_backup::compress(firefox_backup::to_json(tree)?.as_bytes()),
+            BookmarkFormat::FirefoxPlaces => {
+                return Err(AppError::CustomError("places.sqlite cannot be written, use firefox::write_favicons".to_string()));
+            }
+            BookmarkFormat::Safari => {
+                return Err(AppError::CustomError("Safari Bookmarks.plist cannot be written".to_string()));
+            }
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// 根据输入文件和输入、输出格式生成输出文件名
+///
+/// 输出 Firefox 格式时生成的是 `favicons.sqlite` 的副本，因此以其文件名为基础
+pub fn output_filename(input: &str, input_format: BookmarkFormat, output_format: BookmarkFormat) -> String {
+    match (input_format, output_format) {
+        (BookmarkFormat::Netscape, BookmarkFormat::Netscape) => crate::utils::generate_output_filename(input),
+        (_, BookmarkFormat::FirefoxPlaces) => {
+            let favicons = firefox::favicons_path(Path::new(input));
+            crate::utils::generate_output_filename(&favicons.display().to_string())
+        }
+        (_, format) => crate::utils::generate_output_filename_with_extension(input, format.extension()),
+    }
+}
@@ -0,0 +1,443 @@
+//! 书签数据模型
+//!
+//! 以文件夹、书签、分隔符组成的树描述浏览器书签
+
+use url::Url;
+
+/// 书签树
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookmarkTree {
+    /// 书签文件标题（Netscape 格式中的 TITLE / H1）
+    pub title: String,
+    /// 根文件夹，其子节点即顶层书签
+    pub root: Folder,
+    /// 源文件中未识别的顶层字段（如 Chromium 的 `sync_metadata`），写回同一格式时原样保留
+    pub extra_fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// 书签树节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookmarkNode {
+    /// 文件夹
+    Folder(Folder),
+    /// 书签
+    Bookmark(Bookmark),
+    /// 分隔符
+    Separator,
+}
+
+/// 书签文件夹
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Folder {
+    /// 文件夹名称
+    pub title: String,
+    /// 创建时间（Unix 秒）
+    pub add_date: Option<i64>,
+    /// 最后修改时间（Unix 秒）
+    pub last_modified: Option<i64>,
+    /// 是否为书签栏文件夹
+    pub personal_toolbar_folder: bool,
+    /// 浏览器分配的全局唯一标识，Netscape 格式不保存该值
+    pub guid: Option<String>,
+    /// 浏览器内部的节点 ID，写回同一格式时保留
+    pub id: Option<String>,
+    /// 文件夹描述（DD）
+    pub description: Option<String>,
+    /// 未识别的其他属性，属性名为大写
+    pub extra_attributes: Vec<(String, String)>,
+    /// JSON 格式中未识别的字段（如 Chromium 的 `meta_info`），写回同一格式时原样保留
+    pub extra_fields: serde_json::Map<String, serde_json::Value>,
+    /// 子节点
+    pub children: Vec<BookmarkNode>,
+}
+
+/// 书签
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bookmark {
+    /// 书签标题
+    pub title: String,
+    /// 书签地址（HREF）
+    pub url: String,
+    /// 创建时间（Unix 秒）
+    pub add_date: Option<i64>,
+    /// 最后修改时间（Unix 秒）
+    pub last_modified: Option<i64>,
+    /// 图标数据（通常为 data URL）
+    pub icon: Option<String>,
+    /// 图标原始地址
+    pub icon_uri: Option<String>,
+    /// 标签
+    pub tags: Vec<String>,
+    /// 关键字（SHORTCUTURL）
+    pub shortcut_url: Option<String>,
+    /// 书签描述（DD）
+    pub description: Option<String>,
+    /// 浏览器分配的全局唯一标识，Netscape 格式不保存该值
+    pub guid: Option<String>,
+    /// 浏览器内部的节点 ID，写回同一格式时保留
+    pub id: Option<String>,
+    /// 未识别的其他属性，属性名为大写
+    pub extra_attributes: Vec<(String, String)>,
+    /// JSON 格式中未识别的字段（如 Chromium 的 `meta_info`），写回同一格式时原样保留
+    pub extra_fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl BookmarkNode {
+    /// 节点标题，分隔符没有标题
+    pub fn title(&self) -> &str {
+        match self {
+            BookmarkNode::Folder(folder) => &folder.title,
+            BookmarkNode::Bookmark(bookmark) => &bookmark.title,
+            BookmarkNode::Separator => "",
+        }
+    }
+
+    /// 若节点为文件夹则返回其引用
+    pub fn as_folder(&self) -> Option<&Folder> {
+        match self {
+            BookmarkNode::Folder(folder) => Some(folder),
+            _ => None,
+        }
+    }
+
+    /// 若节点为文件夹则返回其可变引用
+    pub fn as_folder_mut(&mut self) -> Option<&mut Folder> {
+        match self {
+            BookmarkNode::Folder(folder) => Some(folder),
+            _ => None,
+        }
+    }
+
+    /// 若节点为书签则返回其引用
+    pub fn as_bookmark(&self) -> Option<&Bookmark> {
+        match self {
+            BookmarkNode::Bookmark(bookmark) => Some(bookmark),
+            _ => None,
+        }
+    }
+
+    /// 若节点为书签则返回其可变引用
+    pub fn as_bookmark_mut(&mut self) -> Option<&mut Bookmark> {
+        match self {
+            BookmarkNode::Bookmark(bookmark) => Some(bookmark),
+            _ => None,
+        }
+    }
+}
+
+impl Folder {
+    /// 创建空文件夹
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 深度优先遍历文件夹内的所有节点（不含文件夹自身）
+    pub fn iter(&self) -> Nodes<'_> {
+        Nodes { stack: vec![self.children.iter()] }
+    }
+
+    /// 深度优先遍历文件夹内的所有书签
+    pub fn bookmarks(&self) -> impl Iterator<Item = &Bookmark> {
+        self.iter().filter_map(BookmarkNode::as_bookmark)
+    }
+
+    /// 深度优先遍历文件夹内的所有子文件夹
+    pub fn folders(&self) -> impl Iterator<Item = &Folder> {
+        self.iter().filter_map(BookmarkNode::as_folder)
+    }
+
+    /// 深度优先收集文件夹内所有书签的可变引用
+    pub fn bookmarks_mut(&mut self) -> Vec<&mut Bookmark> {
+        let mut result = Vec::new();
+        for child in &mut self.children {
+            match child {
+                BookmarkNode::Folder(folder) => result.extend(folder.bookmarks_mut()),
+                BookmarkNode::Bookmark(bookmark) => result.push(bookmark),
+                BookmarkNode::Separator => {}
+            }
+        }
+        result
+    }
+
+    /// 按标题路径查找子文件夹，空路径返回自身
+    pub fn find_folder(&self, path: &[&str]) -> Option<&Folder> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => self.children.iter()
+                .filter_map(BookmarkNode::as_folder)
+                .find(|folder| folder.title == *first)
+                .and_then(|folder| folder.find_folder(rest)),
+        }
+    }
+
+    /// 按标题路径查找子文件夹的可变引用，空路径返回自身
+    pub fn find_folder_mut(&mut self, path: &[&str]) -> Option<&mut Folder> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => self.children.iter_mut()
+                .filter_map(BookmarkNode::as_folder_mut)
+                .find(|folder| folder.title == *first)
+                .and_then(|folder| folder.find_folder_mut(rest)),
+        }
+    }
+
+    /// 按标题路径获取子文件夹，不存在的层级会被依次创建
+    pub fn get_or_create_folder(&mut self, path: &[&str]) -> &mut Folder {
+        let Some((first, rest)) = path.split_first() else {
+            return self;
+        };
+        let index = match self.children.iter().position(|node| matches!(node, BookmarkNode::Folder(folder) if folder.title == *first)) {
+            Some(index) => index,
+            None => {
+                self.children.push(BookmarkNode::Folder(Folder::new(*first)));
+                self.children.len() - 1
+            }
+        };
+        match &mut self.children[index] {
+            BookmarkNode::Folder(folder) => folder.get_or_create_folder(rest),
+            _ => unreachable!("index always points to a folder"),
+        }
+    }
+
+    /// 查找地址完全相同的书签
+    pub fn find_by_url(&self, url: &str) -> Vec<&Bookmark> {
+        self.bookmarks().filter(|bookmark| bookmark.url == url).collect()
+    }
+
+    /// 按关键字搜索书签，匹配标题、地址、标签和描述，不区分大小写
+    pub fn search(&self, query: &str) -> Vec<&Bookmark> {
+        let query = query.to_lowercase();
+        self.bookmarks().filter(|bookmark| bookmark.matches(&query)).collect()
+    }
+
+    /// 在末尾添加书签
+    pub fn add_bookmark(&mut self, bookmark: Bookmark) -> &mut Bookmark {
+        self.children.push(BookmarkNode::Bookmark(bookmark));
+        match self.children.last_mut() {
+            Some(BookmarkNode::Bookmark(bookmark)) => bookmark,
+            _ => unreachable!("a bookmark was just pushed"),
+        }
+    }
+
+    /// 在末尾添加子文件夹
+    pub fn add_folder(&mut self, folder: Folder) -> &mut Folder {
+        self.children.push(BookmarkNode::Folder(folder));
+        match self.children.last_mut() {
+            Some(BookmarkNode::Folder(folder)) => folder,
+            _ => unreachable!("a folder was just pushed"),
+        }
+    }
+
+    /// 递归删除不满足条件的书签，返回删除的数量
+    pub fn retain_bookmarks<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(&Bookmark) -> bool,
+    {
+        self.retain_bookmarks_inner(&mut keep)
+    }
+
+    fn retain_bookmarks_inner<F>(&mut self, keep: &mut F) -> usize
+    where
+        F: FnMut(&Bookmark) -> bool,
+    {
+        let before = self.children.len();
+        self.children.retain(|node| match node {
+            BookmarkNode::Bookmark(bookmark) => keep(bookmark),
+            _ => true,
+        });
+        let mut removed = before - self.children.len();
+        for child in &mut self.children {
+            if let BookmarkNode::Folder(folder) = child {
+                removed += folder.retain_bookmarks_inner(keep);
+            }
+        }
+        removed
+    }
+
+    /// 递归删除空文件夹，返回删除的数量
+    pub fn remove_empty_folders(&mut self) -> usize {
+        let mut removed = 0;
+        for child in &mut self.children {
+            if let BookmarkNode::Folder(folder) = child {
+                removed += folder.remove_empty_folders();
+            }
+        }
+        let before = self.children.len();
+        self.children.retain(|node| !matches!(node, BookmarkNode::Folder(folder) if folder.children.is_empty()));
+        removed + before - self.children.len()
+    }
+}
+
+impl Bookmark {
+    /// 创建书签
+    pub fn new(title: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 书签地址的主机名，无法解析或没有主机名时返回 None
+    pub fn domain(&self) -> Option<String> {
+        Url::parse(&self.url).ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+    }
+
+    /// 是否匹配已转为小写的关键字
+    fn matches(&self, query: &str) -> bool {
+        self.title.to_lowercase().contains(query)
+            || self.url.to_lowercase().contains(query)
+            || self.tags.iter().any(|tag| tag.to_lowercase().contains(query))
+            || self.description.as_ref().is_some_and(|d| d.to_lowercase().contains(query))
+    }
+}
+
+impl BookmarkTree {
+    /// 创建空书签树
+    pub fn new(title: impl Into<String>) -> Self {
+        let title = title.into();
+        Self {
+            root: Folder::new(title.clone()),
+            title,
+            extra_fields: Default::default(),
+        }
+    }
+
+    /// 深度优先遍历树中的所有节点
+    pub fn iter(&self) -> Nodes<'_> {
+        self.root.iter()
+    }
+
+    /// 深度优先遍历树中的所有书签
+    pub fn bookmarks(&self) -> impl Iterator<Item = &Bookmark> {
+        self.root.bookmarks()
+    }
+
+    /// 深度优先收集树中所有书签的可变引用
+    pub fn bookmarks_mut(&mut self) -> Vec<&mut Bookmark> {
+        self.root.bookmarks_mut()
+    }
+
+    /// 书签总数
+    pub fn bookmark_count(&self) -> usize {
+        self.bookmarks().count()
+    }
+
+    /// 按关键字搜索书签，匹配标题、地址、标签和描述，不区分大小写
+    pub fn search(&self, query: &str) -> Vec<&Bookmark> {
+        self.root.search(query)
+    }
+}
+
+/// 书签树节点的深度优先迭代器
+pub struct Nodes<'a> {
+    stack: Vec<std::slice::Iter<'a, BookmarkNode>>,
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = &'a BookmarkNode;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let iter = self.stack.last_mut()?;
+            match iter.next() {
+                Some(node) => {
+                    if let BookmarkNode::Folder(folder) = node {
+                        self.stack.push(folder.children.iter());
+                    }
+                    return Some(node);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Root: A, [Work: B, [Deep: C], ---], D
+    fn sample() -> BookmarkTree {
+        let mut tree = BookmarkTree::new("Bookmarks");
+        tree.root.add_bookmark(Bookmark::new("A", "https://a.test/"));
+        let work = tree.root.add_folder(Folder::new("Work"));
+        work.add_bookmark(Bookmark { tags: vec!["Rust".to_string()], ..Bookmark::new("B", "https://b.test/") });
+        work.add_folder(Folder::new("Deep")).add_bookmark(Bookmark {
+            description: Some("Docs for the crate".to_string()),
+            ..Bookmark::new("C", "https://www.c.test/path")
+        });
+        work.children.push(BookmarkNode::Separator);
+        tree.root.add_bookmark(Bookmark::new("D", "https://a.test/"));
+        tree
+    }
+
+    #[test]
+    fn traversal_is_depth_first_in_document_order() {
+        let tree = sample();
+        let titles: Vec<&str> = tree.iter().map(BookmarkNode::title).collect();
+        assert_eq!(titles, vec!["A", "Work", "B", "Deep", "C", "", "D"]);
+        let bookmarks: Vec<&str> = tree.bookmarks().map(|bookmark| bookmark.title.as_str()).collect();
+        assert_eq!(bookmarks, vec!["A", "B", "C", "D"]);
+        let folders: Vec<&str> = tree.root.folders().map(|folder| folder.title.as_str()).collect();
+        assert_eq!(folders, vec!["Work", "Deep"]);
+        assert_eq!(tree.bookmark_count(), 4);
+
+        let mut tree = sample();
+        let mutable: Vec<String> = tree.bookmarks_mut().into_iter().map(|bookmark| bookmark.title.clone()).collect();
+        assert_eq!(mutable, vec!["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn search_matches_title_url_tags_and_description() {
+        let tree = sample();
+        let titles = |query: &str| tree.search(query).into_iter().map(|bookmark| bookmark.title.clone()).collect::<Vec<_>>();
+        assert_eq!(titles("rust"), vec!["B"]);
+        assert_eq!(titles("CRATE"), vec!["C"]);
+        assert_eq!(titles("a.test"), vec!["A", "D"]);
+        assert_eq!(titles("c"), vec!["C"]);
+        assert!(titles("missing").is_empty());
+        assert_eq!(tree.root.find_by_url("https://a.test/").len(), 2);
+        assert_eq!(tree.root.bookmarks().nth(2).unwrap().domain().as_deref(), Some("www.c.test"));
+    }
+
+    #[test]
+    fn folders_are_found_and_created_by_path() {
+        let mut tree = sample();
+        assert_eq!(tree.root.find_folder(&["Work", "Deep"]).unwrap().children.len(), 1);
+        assert!(tree.root.find_folder(&["Work", "Missing"]).is_none());
+        assert_eq!(tree.root.find_folder(&[]).unwrap().title, "Bookmarks");
+
+        tree.root.find_folder_mut(&["Work"]).unwrap().add_bookmark(Bookmark::new("E", "https://e.test/"));
+        tree.root.get_or_create_folder(&["Work", "New", "Inner"]).add_bookmark(Bookmark::new("F", "https://f.test/"));
+        // 已存在的层级不会重复创建
+        tree.root.get_or_create_folder(&["Work", "New"]);
+        assert_eq!(tree.root.find_folder(&["Work"]).unwrap().folders().filter(|folder| folder.title == "New").count(), 1);
+        let bookmarks: Vec<&str> = tree.bookmarks().map(|bookmark| bookmark.title.as_str()).collect();
+        assert_eq!(bookmarks, vec!["A", "B", "C", "E", "F", "D"]);
+    }
+
+    #[test]
+    fn removing_bookmarks_and_empty_folders() {
+        let mut tree = sample();
+        assert_eq!(tree.root.retain_bookmarks(|bookmark| bookmark.url != "https://a.test/"), 2);
+        assert_eq!(tree.root.retain_bookmarks(|bookmark| bookmark.title != "C"), 1);
+        assert_eq!(tree.bookmark_count(), 1);
+
+        // Deep 为空被删除，Work 仍有书签和分隔符
+        assert_eq!(tree.root.remove_empty_folders(), 1);
+        assert!(tree.root.find_folder(&["Work", "Deep"]).is_none());
+        assert!(tree.root.find_folder(&["Work"]).is_some());
+
+        tree.root.retain_bookmarks(|_| false);
+        tree.root.find_folder_mut(&["Work"]).unwrap().children.clear();
+        assert_eq!(tree.root.remove_empty_folders(), 1);
+        assert!(tree.root.children.is_empty());
+    }
+}
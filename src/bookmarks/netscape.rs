@@ -0,0 +1,335 @@
+//! Netscape 书签格式
+//!
+//! 解析和生成浏览器导出的 `NETSCAPE-Bookmark-file-1` HTML 文件
+
+use scraper::{ElementRef, Html, Selector};
+
+use crate::errors::{AppError, AppResult};
+use super::model::{Bookmark, BookmarkNode, BookmarkTree, Folder};
+
+/// 解析 Netscape 书签 HTML
+///
+/// HTML 由 html5ever 按浏览器规则容错解析，标签和属性名大小写不敏感，
+/// 属性值可以不加引号，实体会被正确解码
+pub fn parse(html: &str) -> AppResult<BookmarkTree> {
+    let document = Html::parse_document(html);
+
+    let title = ["title", "h1"].iter()
+        .filter_map(|name| Selector::parse(name).ok())
+        .find_map(|selector| document.select(&selector).next())
+        .map(|element| element_text(&element))
+        .unwrap_or_default();
+
+    let dl_selector = Selector::parse("dl")
+        .map_err(|e| AppError::CustomError(format!("Selector error: {}", e)))?;
+    let root_list = document.select(&dl_selector).next()
+        .ok_or_else(|| AppError::CustomError("Bookmark list <DL> not found".to_string()))?;
+
+    let mut root = Folder::new(title.clone());
+    root.children = parse_list(root_list);
+    Ok(BookmarkTree { title, root, ..Default::default() })
+}
+
+/// 解析一个 DL 列表（或被当作透明容器的 P）中的节点
+fn parse_list(list: ElementRef) -> Vec<BookmarkNode> {
+    let mut nodes = Vec::new();
+    for child in list.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "dt" => parse_entry(child, &mut nodes),
+            "hr" => nodes.push(BookmarkNode::Separator),
+            "dd" => parse_description(child, &mut nodes),
+            "p" | "dl" => nodes.extend(parse_list(child)),
+            _ => {}
+        }
+    }
+    nodes
+}
+
+/// 解析一个 DT 条目：H3 + DL 为文件夹，A 为书签
+fn parse_entry(entry: ElementRef, nodes: &mut Vec<BookmarkNode>) {
+    let mut pending_folder: Option<Folder> = None;
+    for child in entry.children().filter_map(ElementRef::wrap) {
+        match child.value().name() {
+            "h3" => {
+                if let Some(folder) = pending_folder.take() {
+                    nodes.push(BookmarkNode::Folder(folder));
+                }
+                pending_folder = Some(parse_folder_header(child));
+            }
+            "dl" => match pending_folder.take() {
+                Some(mut folder) => {
+                    folder.children = parse_list(child);
+                    nodes.push(BookmarkNode::Folder(folder));
+                }
+                None => nodes.extend(parse_list(child)),
+            },
+            "a" => nodes.push(BookmarkNode::Bookmark(parse_bookmark(child))),
+            "hr" => nodes.push(BookmarkNode::Separator),
+            "dt" | "p" => parse_entry(child, nodes),
+            _ => {}
+        }
+    }
+    if let Some(folder) = pending_folder {
+        nodes.push(BookmarkNode::Folder(folder));
+    }
+}
+
+/// 解析 DD 描述
+///
+/// DD 会隐式关闭前面的 DT，因此文件夹描述之后的 DL 会落在 DD 内部
+fn parse_description(dd: ElementRef, nodes: &mut Vec<BookmarkNode>) {
+    let description: String = dd.children()
+        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    let mut nested = Vec::new();
+    for child in dd.children().filter_map(ElementRef::wrap) {
+        if matches!(child.value().name(), "dl" | "p") {
+            nested.extend(parse_list(child));
+        }
+    }
+
+    match nodes.last_mut() {
+        Some(BookmarkNode::Folder(folder)) => {
+            if !description.is_empty() {
+                folder.description = Some(description);
+            }
+            folder.children.extend(nested);
+        }
+        Some(BookmarkNode::Bookmark(bookmark)) => {
+            if !description.is_empty() {
+                bookmark.description = Some(description);
+            }
+            nodes.extend(nested);
+        }
+        _ => nodes.extend(nested),
+    }
+}
+
+/// 从 H3 元素解析文件夹属性
+fn parse_folder_header(h3: ElementRef) -> Folder {
+    let mut folder = Folder::new(element_text(&h3));
+    for (name, value) in h3.value().attrs() {
+        match name {
+            "add_date" => folder.add_date = parse_timestamp(value),
+            "last_modified" => folder.last_modified = parse_timestamp(value),
+            "personal_toolbar_folder" => folder.personal_toolbar_folder = value.eq_ignore_ascii_case("true"),
+            _ => folder.extra_attributes.push((name.to_ascii_uppercase(), value.to_string())),
+        }
+    }
+    folder
+}
+
+/// 从 A 元素解析书签属性
+fn parse_bookmark(a: ElementRef) -> Bookmark {
+    let mut bookmark = Bookmark::new(element_text(&a), "");
+    for (name, value) in a.value().attrs() {
+        match name {
+            "href" => bookmark.url = value.trim().to_string(),
+            "add_date" => bookmark.add_date = parse_timestamp(value),
+            "last_modified" => bookmark.last_modified = parse_timestamp(value),
+            "icon" => bookmark.icon = non_empty(value),
+            "icon_uri" => bookmark.icon_uri = non_empty(value),
+            "tags" => bookmark.tags = value.split(',')
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            "shortcuturl" => bookmark.shortcut_url = non_empty(value),
+            _ => bookmark.extra_attributes.push((name.to_ascii_uppercase(), value.to_string())),
+        }
+    }
+    bookmark
+}
+
+fn element_text(element: &ElementRef) -> String {
+    element.text().collect::<String>().trim().to_string()
+}
+
+fn parse_timestamp(value: &str) -> Option<i64> {
+    value.trim().parse().ok()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// 将书签树生成为 Netscape 书签 HTML
+pub fn to_html(tree: &BookmarkTree) -> String {
+    let title = if tree.title.is_empty() { "Bookmarks" } else { tree.title.as_str() };
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE NETSCAPE-Bookmark-file-1>\n");
+    html.push_str("<!-- This is an automatically generated file.\n");
+    html.push_str("     It will be read and overwritten.\n");
+    html.push_str("     DO NOT EDIT! -->\n");
+    html.push_str("<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n");
+    html.push_str(&format!("<TITLE>{}</TITLE>\n", escape_text(title)));
+    html.push_str(&format!("<H1>{}</H1>\n", escape_text(title)));
+    html.push_str("<DL><p>\n");
+    write_children(&tree.root.children, 1, &mut html);
+    html.push_str("</DL><p>\n");
+    html
+}
+
+fn write_children(children: &[BookmarkNode], depth: usize, html: &mut String) {
+    let indent = "    ".repeat(depth);
+    for child in children {
+        match child {
+            BookmarkNode::Folder(folder) => {
+                html.push_str(&format!("{}<DT><H3", indent));
+                push_date_attribute(html, "ADD_DATE", folder.add_date);
+                push_date_attribute(html, "LAST_MODIFIED", folder.last_modified);
+                if folder.personal_toolbar_folder {
+                    push_attribute(html, "PERSONAL_TOOLBAR_FOLDER", "true");
+                }
+                for (name, value) in &folder.extra_attributes {
+                    push_attribute(html, name, value);
+                }
+                html.push_str(&format!(">{}</H3>\n", escape_text(&folder.title)));
+                if let Some(description) = &folder.description {
+                    html.push_str(&format!("{}<DD>{}\n", indent, escape_text(description)));
+                }
+                html.push_str(&format!("{}<DL><p>\n", indent));
+                write_children(&folder.children, depth + 1, html);
+                html.push_str(&format!("{}</DL><p>\n", indent));
+            }
+            BookmarkNode::Bookmark(bookmark) => {
+                html.push_str(&format!("{}<DT><A", indent));
+                push_attribute(html, "HREF", &bookmark.url);
+                push_date_attribute(html, "ADD_DATE", bookmark.add_date);
+                push_date_attribute(html, "LAST_MODIFIED", bookmark.last_modified);
+                if let Some(icon_uri) = &bookmark.icon_uri {
+                    push_attribute(html, "ICON_URI", icon_uri);
+                }
+                if let Some(icon) = &bookmark.icon {
+                    push_attribute(html, "ICON", icon);
+                }
+                if let Some(shortcut_url) = &bookmark.shortcut_url {
+                    push_attribute(html, "SHORTCUTURL", shortcut_url);
+                }
+                if !bookmark.tags.is_empty() {
+                    push_attribute(html, "TAGS", &bookmark.tags.join(","));
+                }
+                for (name, value) in &bookmark.extra_attributes {
+                    push_attribute(html, name, value);
+                }
+                html.push_str(&format!(">{}</A>\n", escape_text(&bookmark.title)));
+                if let Some(description) = &bookmark.description {
+                    html.push_str(&format!("{}<DD>{}\n", indent, escape_text(description)));
+                }
+            }
+            BookmarkNode::Separator => html.push_str(&format!("{}<HR>\n", indent)),
+        }
+    }
+}
+
+fn push_attribute(html: &mut String, name: &str, value: &str) {
+    html.push_str(&format!(" {}=\"{}\"", name, escape_attribute(value)));
+}
+
+fn push_date_attribute(html: &mut String, name: &str, value: Option<i64>) {
+    if let Some(value) = value {
+        push_attribute(html, name, &value.to_string());
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn escape_attribute(value: &str) -> String {
+    escape_text(value).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000" LAST_MODIFIED="1700000100" PERSONAL_TOOLBAR_FOLDER="true">Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/" ADD_DATE="1700000001" ICON="data:image/png;base64,AAAA" TAGS="a, b">Example &amp; Co</A>
+        <DD>An example site
+        <DT><H3>Nested</H3>
+        <DL><p>
+            <DT><A HREF="https://www.rust-lang.org/" SHORTCUTURL="rust">Rust</A>
+        </DL><p>
+    </DL><p>
+    <HR>
+    <DT><A HREF="https://plain.test/" DATA-CUSTOM="kept">Plain</A>
+</DL><p>
+"#;
+
+    fn bookmark<'a>(tree: &'a BookmarkTree, title: &str) -> &'a Bookmark {
+        tree.root.bookmarks().find(|bookmark| bookmark.title == title).unwrap()
+    }
+
+    #[test]
+    fn parses_folders_bookmarks_and_attributes() {
+        let tree = parse(SAMPLE).unwrap();
+        assert_eq!(tree.title, "Bookmarks");
+        assert_eq!(tree.root.children.len(), 3);
+        assert!(matches!(tree.root.children[1], BookmarkNode::Separator));
+
+        let toolbar = tree.root.find_folder(&["Toolbar"]).unwrap();
+        assert!(toolbar.personal_toolbar_folder);
+        assert_eq!(toolbar.add_date, Some(1700000000));
+        assert_eq!(toolbar.last_modified, Some(1700000100));
+        assert!(tree.root.find_folder(&["Toolbar", "Nested"]).is_some());
+
+        let example = bookmark(&tree, "Example & Co");
+        assert_eq!(example.url, "https://example.com/");
+        assert_eq!(example.add_date, Some(1700000001));
+        assert_eq!(example.icon.as_deref(), Some("data:image/png;base64,AAAA"));
+        assert_eq!(example.tags, vec!["a", "b"]);
+        assert_eq!(example.description.as_deref(), Some("An example site"));
+        assert_eq!(bookmark(&tree, "Rust").shortcut_url.as_deref(), Some("rust"));
+        assert_eq!(bookmark(&tree, "Plain").extra_attributes, vec![("DATA-CUSTOM".to_string(), "kept".to_string())]);
+    }
+
+    #[test]
+    fn tolerates_lowercase_tags_and_unquoted_attributes() {
+        let html = r#"<title>Mine</title>
+<dl><p>
+<dt><h3 add_date=1700000000>Folder</h3>
+<dl><p>
+<dt><a href=https://example.com/ add_date=1700000001>Lower</a>
+<dt><a href='https://example.com/?q="quoted"'>Single quoted</a>
+<dt><a href="https://example.com/?q=&quot;entity&quot;&amp;x=1">Entity</a>
+</dl><p>
+</dl>"#;
+        let tree = parse(html).unwrap();
+        assert_eq!(tree.title, "Mine");
+        let folder = tree.root.find_folder(&["Folder"]).unwrap();
+        assert_eq!(folder.add_date, Some(1700000000));
+        assert_eq!(folder.children.len(), 3);
+        assert_eq!(bookmark(&tree, "Lower").url, "https://example.com/");
+        assert_eq!(bookmark(&tree, "Lower").add_date, Some(1700000001));
+        assert_eq!(bookmark(&tree, "Single quoted").url, r#"https://example.com/?q="quoted""#);
+        assert_eq!(bookmark(&tree, "Entity").url, r#"https://example.com/?q="entity"&x=1"#);
+    }
+
+    #[test]
+    fn missing_list_is_an_error() {
+        assert!(parse("<html><body>No bookmarks</body></html>").is_err());
+    }
+
+    #[test]
+    fn to_html_round_trips() {
+        let mut tree = parse(SAMPLE).unwrap();
+        tree.root.add_bookmark(Bookmark::new(r#"Quotes "and" <tags>"#, r#"https://example.com/?q="a"&b=<c>"#));
+        let html = to_html(&tree);
+        assert_eq!(parse(&html).unwrap(), tree);
+        assert_eq!(to_html(&parse(&html).unwrap()), html);
+    }
+}
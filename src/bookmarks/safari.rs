@@ -0,0 +1,258 @@
+//! Safari 书签
+//!
+//! 读取 Safari 的 `Bookmarks.plist`（二进制或 XML 格式）。
+//! 文件夹为 `WebBookmarkTypeList`，书签为 `WebBookmarkTypeLeaf`，历史记录等入口为 `WebBookmarkTypeProxy`，读取时跳过
+
+use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use plist::{Dictionary, Value};
+
+use crate::errors::{AppError, AppResult};
+use super::model::{Bookmark, BookmarkNode, BookmarkTree, Folder};
+
+const TYPE_LIST: &str = "WebBookmarkTypeList";
+const TYPE_LEAF: &str = "WebBookmarkTypeLeaf";
+
+const BOOKMARKS_BAR: &str = "BookmarksBar";
+const BOOKMARKS_MENU: &str = "BookmarksMenu";
+const READING_LIST: &str = "com.apple.ReadingList";
+
+/// 判断数据是否为 Safari 书签文件
+pub fn is_safari(bytes: &[u8]) -> bool {
+    let is_plist = bytes.starts_with(b"bplist00")
+        || String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).contains("<plist");
+    is_plist && Value::from_reader(Cursor::new(bytes)).ok()
+        .and_then(|value| value.as_dictionary().and_then(|dict| dict.get("WebBookmarkType")).and_then(|t| t.as_string()).map(|t| t == TYPE_LIST))
+        .unwrap_or(false)
+}
+
+/// 解析 Safari 书签文件
+///
+/// 收藏栏作为书签栏文件夹，书签菜单的内容放在顶层，阅读列表作为单独的文件夹
+pub fn parse(bytes: &[u8]) -> AppResult<BookmarkTree> {
+    let value = Value::from_reader(Cursor::new(bytes))
+        .map_err(|e| AppError::CustomError(format!("Invalid Safari Bookmarks.plist: {}", e)))?;
+    let root = value.as_dictionary()
+        .ok_or_else(|| AppError::CustomError("Invalid Safari Bookmarks.plist: root is not a dictionary".to_string()))?;
+
+    let mut tree = BookmarkTree::new("Bookmarks");
+    for child in children(root) {
+        let title = child.get("Title").and_then(|t| t.as_string()).unwrap_or_default();
+        match (node_type(child), title) {
+            (TYPE_LIST, BOOKMARKS_BAR) => {
+                let mut favorites = convert_folder(child);
+                favorites.title = "Favorites".to_string();
+                favorites.personal_toolbar_folder = true;
+                tree.root.children.push(BookmarkNode::Folder(favorites));
+            }
+            (TYPE_LIST, BOOKMARKS_MENU) => tree.root.children.extend(convert_children(child)),
+            (TYPE_LIST, READING_LIST) => {
+                let mut reading_list = convert_folder(child);
+                reading_list.title = "Reading List".to_string();
+                if !reading_list.children.is_empty() {
+                    tree.root.children.push(BookmarkNode::Folder(reading_list));
+                }
+            }
+            _ => tree.root.children.extend(convert_node(child)),
+        }
+    }
+    Ok(tree)
+}
+
+fn node_type(dict: &Dictionary) -> &str {
+    dict.get("WebBookmarkType").and_then(|t| t.as_string()).unwrap_or_default()
+}
+
+fn children(dict: &Dictionary) -> impl Iterator<Item = &Dictionary> {
+    dict.get("Children")
+        .and_then(|c| c.as_array())
+        .map(|c| c.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|child| child.as_dictionary())
+}
+
+fn convert_children(dict: &Dictionary) -> Vec<BookmarkNode> {
+    children(dict).filter_map(convert_node).collect()
+}
+
+fn convert_node(dict: &Dictionary) -> Option<BookmarkNode> {
+    match node_type(dict) {
+        TYPE_LIST => Some(BookmarkNode::Folder(convert_folder(dict))),
+        TYPE_LEAF => Some(BookmarkNode::Bookmark(convert_bookmark(dict)?)),
+        _ => None,
+    }
+}
+
+fn convert_folder(dict: &Dictionary) -> Folder {
+    let mut folder = Folder::new(dict.get("Title").and_then(|t| t.as_string()).unwrap_or_default());
+    folder.guid = dict.get("WebBookmarkUUID").and_then(|u| u.as_string()).map(str::to_string);
+    folder.children = convert_children(dict);
+    folder
+}
+
+fn convert_bookmark(dict: &Dictionary) -> Option<Bookmark> {
+    let url = dict.get("URLString").and_then(|u| u.as_string())?;
+    let title = dict.get("URIDictionary")
+        .and_then(|d| d.as_dictionary())
+        .and_then(|d| d.get("title"))
+        .and_then(|t| t.as_string())
+        .unwrap_or(url);
+    let mut bookmark = Bookmark::new(title, url);
+    bookmark.guid = dict.get("WebBookmarkUUID").and_then(|u| u.as_string()).map(str::to_string);
+    if let Some(reading_list) = dict.get("ReadingList").and_then(|r| r.as_dictionary()) {
+        bookmark.add_date = reading_list.get("DateAdded").and_then(|d| d.as_date()).and_then(to_unix);
+        bookmark.description = reading_list.get("PreviewText").and_then(|t| t.as_string()).map(str::to_string);
+    }
+    Some(bookmark)
+}
+
+fn to_unix(date: plist::Date) -> Option<i64> {
+    SystemTime::from(date).duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bookmarks::netscape;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Children</key>
+    <array>
+        <dict>
+            <key>Title</key><string>History</string>
+            <key>WebBookmarkType</key><string>WebBookmarkTypeProxy</string>
+            <key>WebBookmarkIdentifier</key><string>History</string>
+        </dict>
+        <dict>
+            <key>Title</key><string>BookmarksBar</string>
+            <key>WebBookmarkType</key><string>WebBookmarkTypeList</string>
+            <key>WebBookmarkUUID</key><string>0C0B8F2E-5A4B-4C7E-9F6D-111111111111</string>
+            <key>Children</key>
+            <array>
+                <dict>
+                    <key>URIDictionary</key>
+                    <dict><key>title</key><string>Example</string></dict>
+                    <key>URLString</key><string>https://example.com/</string>
+                    <key>WebBookmarkType</key><string>WebBookmarkTypeLeaf</string>
+                    <key>WebBookmarkUUID</key><string>0C0B8F2E-5A4B-4C7E-9F6D-222222222222</string>
+                </dict>
+                <dict>
+                    <key>Title</key><string>Docs</string>
+                    <key>WebBookmarkType</key><string>WebBookmarkTypeList</string>
+                    <key>Children</key>
+                    <array>
+                        <dict>
+                            <key>URLString</key><string>https://www.rust-lang.org/</string>
+                            <key>WebBookmarkType</key><string>WebBookmarkTypeLeaf</string>
+                        </dict>
+                    </array>
+                </dict>
+            </array>
+        </dict>
+        <dict>
+            <key>Title</key><string>BookmarksMenu</string>
+            <key>WebBookmarkType</key><string>WebBookmarkTypeList</string>
+            <key>Children</key>
+            <array>
+                <dict>
+                    <key>URIDictionary</key>
+                    <dict><key>title</key><string>Menu item</string></dict>
+                    <key>URLString</key><string>https://example.org/</string>
+                    <key>WebBookmarkType</key><string>WebBookmarkTypeLeaf</string>
+                </dict>
+            </array>
+        </dict>
+        <dict>
+            <key>Title</key><string>com.apple.ReadingList</string>
+            <key>WebBookmarkType</key><string>WebBookmarkTypeList</string>
+            <key>Children</key>
+            <array>
+                <dict>
+                    <key>ReadingList</key>
+                    <dict>
+                        <key>DateAdded</key><date>2023-11-14T22:13:20Z</date>
+                        <key>PreviewText</key><string>Later</string>
+                    </dict>
+                    <key>URIDictionary</key>
+                    <dict><key>title</key><string>Article</string></dict>
+                    <key>URLString</key><string>https://example.net/article</string>
+                    <key>WebBookmarkType</key><string>WebBookmarkTypeLeaf</string>
+                </dict>
+            </array>
+        </dict>
+    </array>
+    <key>Title</key><string></string>
+    <key>WebBookmarkFileVersion</key><integer>1</integer>
+    <key>WebBookmarkType</key><string>WebBookmarkTypeList</string>
+</dict>
+</plist>"#;
+
+    /// 与 XML 内容相同的二进制 plist
+    fn binary_sample() -> Vec<u8> {
+        let value = Value::from_reader(Cursor::new(SAMPLE.as_bytes())).unwrap();
+        let mut bytes = Vec::new();
+        value.to_writer_binary(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn assert_sample(tree: &BookmarkTree) {
+        assert_eq!(tree.root.children.len(), 3);
+
+        let favorites = tree.root.children[0].as_folder().unwrap();
+        assert_eq!(favorites.title, "Favorites");
+        assert!(favorites.personal_toolbar_folder);
+        let example = favorites.children[0].as_bookmark().unwrap();
+        assert_eq!((example.title.as_str(), example.url.as_str()), ("Example", "https://example.com/"));
+        // 没有标题的书签使用网址作为标题
+        let docs = tree.root.find_folder(&["Favorites", "Docs"]).unwrap();
+        assert_eq!(docs.children[0].title(), "https://www.rust-lang.org/");
+
+        assert_eq!(tree.root.children[1].title(), "Menu item");
+
+        let reading_list = tree.root.children[2].as_folder().unwrap();
+        assert_eq!(reading_list.title, "Reading List");
+        let article = reading_list.children[0].as_bookmark().unwrap();
+        assert_eq!(article.add_date, Some(1_700_000_000));
+        assert_eq!(article.description.as_deref(), Some("Later"));
+    }
+
+    #[test]
+    fn parses_xml_plist() {
+        assert!(is_safari(SAMPLE.as_bytes()));
+        let tree = parse(SAMPLE.as_bytes()).unwrap();
+        assert_sample(&tree);
+        let example = tree.root.bookmarks().next().unwrap();
+        assert_eq!(example.guid.as_deref(), Some("0C0B8F2E-5A4B-4C7E-9F6D-222222222222"));
+    }
+
+    #[test]
+    fn parses_binary_plist() {
+        let bytes = binary_sample();
+        assert!(bytes.starts_with(b"bplist00"));
+        assert!(is_safari(&bytes));
+        assert_eq!(parse(&bytes).unwrap(), parse(SAMPLE.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn other_plists_are_not_safari_bookmarks() {
+        let mut bytes = Vec::new();
+        Value::Dictionary(Dictionary::new()).to_writer_binary(&mut bytes).unwrap();
+        assert!(!is_safari(&bytes));
+        assert!(!is_safari(b"<html><body></body></html>"));
+        assert!(parse(b"not a plist").is_err());
+    }
+
+    #[test]
+    fn netscape_output_round_trips() {
+        let tree = parse(&binary_sample()).unwrap();
+        let html = netscape::to_html(&tree);
+        let written = netscape::parse(&html).unwrap();
+        assert_sample(&written);
+        assert_eq!(netscape::to_html(&written), html);
+    }
+}
@@ -0,0 +1,49 @@
+//! 缓存配置模块
+
+use serde::{Deserialize, Serialize};
+
+/// 缓存的存储方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheBackend {
+    /// 单个 JSON 文件，每次保存时整体写入
+    Json,
+    /// 嵌入式 SQLite 数据库，逐条写入，图标以二进制保存
+    #[default]
+    Sqlite,
+}
+
+impl CacheBackend {
+    /// 所有存储方式，用于界面选择
+    pub const ALL: [CacheBackend; 2] = [CacheBackend::Sqlite, CacheBackend::Json];
+
+    /// 存储方式名称的翻译键
+    pub fn message_key(self) -> &'static str {
+        match self {
+            CacheBackend::Json => "cache_backend_json",
+            CacheBackend::Sqlite => "cache_backend_sqlite",
+        }
+    }
+}
+
+/// 缓存配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// 缓存的存储方式，切换后下次处理时自动迁移已有缓存
+    pub backend: CacheBackend,
+    /// 获取到的图标的有效期（天），过期后重新获取，0 表示永不过期
+    pub positive_ttl_days: u64,
+    /// 永久性失败（如 404）的有效期（天），过期后重新尝试，0 表示永不过期
+    pub negative_ttl_days: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackend::default(),
+            positive_ttl_days: 30,
+            negative_ttl_days: 7,
+        }
+    }
+}
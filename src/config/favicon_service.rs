@@ -2,24 +2,109 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Favicon 服务类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceKind {
+    /// 按 URL 模板请求第三方服务
+    #[default]
+    Template,
+    /// 直接请求书签页面，从页面声明中查找图标
+    Direct,
+    /// 从本地目录读取以域名命名的图标文件
+    LocalDirectory,
+    /// 调用外部命令获取图标
+    Command,
+}
+
+impl ServiceKind {
+    /// 可以在界面中添加的服务类型，直接获取服务已内置
+    pub const ADDABLE: [ServiceKind; 3] = [ServiceKind::Template, ServiceKind::LocalDirectory, ServiceKind::Command];
+
+    /// 类型名称的翻译键
+    pub fn message_key(self) -> &'static str {
+        match self {
+            ServiceKind::Template => "service_kind_template",
+            ServiceKind::Direct => "service_kind_direct",
+            ServiceKind::LocalDirectory => "service_kind_local_directory",
+            ServiceKind::Command => "service_kind_command",
+        }
+    }
+
+    /// `url_template` 填写说明的翻译键
+    pub fn hint_key(self) -> &'static str {
+        match self {
+            ServiceKind::Template => "domain_placeholder_hint",
+            ServiceKind::Direct => "direct_service_hint",
+            ServiceKind::LocalDirectory => "local_directory_hint",
+            ServiceKind::Command => "command_hint",
+        }
+    }
+}
+
 /// Favicon 服务提供商配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FaviconService {
     /// 服务名称
     pub name: String,
     /// API URL 模板，使用 {domain} 作为域名占位符
+    ///
+    /// 本地目录服务为目录路径，外部命令服务为命令行，可使用 {domain}、{url}、{title} 占位符
     pub url_template: String,
     /// 是否为默认服务
     pub is_default: bool,
+    /// 服务类型
+    #[serde(default)]
+    pub kind: ServiceKind,
+    /// 是否参与获取
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 服务不认识域名时返回的默认图标哈希，匹配时换下一个服务
+    ///
+    /// 支持 `md5:<十六进制>` 内容哈希和 `dhash:<十六进制>` 感知哈希
+    #[serde(default)]
+    pub placeholder_hashes: Vec<String>,
+    /// 是否先请求一个不存在的域名，把返回的图标也视为占位图标，只用于 URL 模板服务
+    #[serde(default = "default_enabled")]
+    pub probe_placeholder: bool,
+    /// 来自导入配置的外部命令服务，用户在服务选项卡中确认前不会运行
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub needs_confirmation: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl FaviconService {
+    /// 内置的直接获取服务
+    pub fn direct() -> Self {
+        Self {
+            name: "Direct".to_string(),
+            url_template: String::new(),
+            is_default: false,
+            kind: ServiceKind::Direct,
+            enabled: true,
+            placeholder_hashes: Vec::new(),
+            probe_placeholder: false,
+            needs_confirmation: false,
+        }
+    }
+
+    /// 获取favicon URL
+    pub fn favicon_url(&self, domain: &str) -> String {
+        self.url_template.replace("{domain}", domain)
+    }
 }
 
 /// Favicon 服务配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FaviconServiceConfig {
-    /// Favicon 服务提供商列表
+    /// Favicon 服务提供商列表，按顺序依次尝试，直到获取成功
     pub services: Vec<FaviconService>,
-    /// 当前选择的服务索引
-    pub current_service_index: usize,
+    /// 旧版本配置中选择的服务索引，加载时迁移为服务顺序
+    #[serde(default, skip_serializing)]
+    pub current_service_index: Option<usize>,
 }
 
 impl Default for FaviconServiceConfig {
@@ -30,14 +115,119 @@ impl Default for FaviconServiceConfig {
                     name: "Google".to_string(),
                     url_template: "https://www.google.com/s2/favicons?sz=64&domain={domain}".to_string(),
                     is_default: true,
+                    kind: ServiceKind::Template,
+                    enabled: true,
+                    placeholder_hashes: Vec::new(),
+                    probe_placeholder: true,
+                    needs_confirmation: false,
                 },
                 FaviconService {
                     name: "DuckDuckGo".to_string(),
                     url_template: "https://icons.duckduckgo.com/ip3/{domain}.ico".to_string(),
                     is_default: false,
+                    kind: ServiceKind::Template,
+                    enabled: true,
+                    placeholder_hashes: Vec::new(),
+                    probe_placeholder: true,
+                    needs_confirmation: false,
                 },
+                FaviconService::direct(),
             ],
-            current_service_index: 0,
+            current_service_index: None,
+        }
+    }
+}
+
+impl FaviconServiceConfig {
+    /// 按顺序返回启用的服务，待确认的外部命令服务不参与获取
+    pub fn chain(&self) -> impl Iterator<Item = &FaviconService> {
+        self.services.iter().filter(|service| service.enabled && !service.needs_confirmation)
+    }
+
+    /// 停用外部命令服务并标记为待确认，返回受影响的服务数
+    ///
+    /// 导入他人分享的配置时调用，避免导入后直接运行其中的命令
+    pub fn require_command_confirmation(&mut self) -> usize {
+        let mut count = 0;
+        for service in self.services.iter_mut().filter(|service| service.kind == ServiceKind::Command) {
+            service.enabled = false;
+            service.needs_confirmation = true;
+            count += 1;
+        }
+        count
+    }
+
+    /// 交换两个服务的顺序
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a < self.services.len() && b < self.services.len() {
+            self.services.swap(a, b);
+        }
+    }
+
+    /// 迁移旧版本配置：补充缺少的内置服务，并把原来选择的服务移到最前面
+    pub fn migrate(&mut self) {
+        // 早期版本把直接获取服务也标记为默认服务，默认服务只有一个
+        for service in self.services.iter_mut().filter(|service| service.kind == ServiceKind::Direct) {
+            service.is_default = false;
+        }
+        // 已有配置中补充的直接获取服务默认停用，避免升级后自动向书签站点发起请求
+        if !self.services.iter().any(|service| service.kind == ServiceKind::Direct) {
+            self.services.push(FaviconService { enabled: false, ..FaviconService::direct() });
+        }
+        if let Some(index) = self.current_service_index.take() {
+            if index < self.services.len() {
+                let service = self.services.remove(index);
+                self.services.insert(0, service);
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_adds_disabled_direct_service() {
+        let mut config = FaviconServiceConfig::default();
+        config.services.retain(|service| service.kind != ServiceKind::Direct);
+        config.current_service_index = Some(1);
+        config.migrate();
+
+        let names: Vec<&str> = config.services.iter().map(|service| service.name.as_str()).collect();
+        assert_eq!(names, ["DuckDuckGo", "Google", "Direct"]);
+        let direct = &config.services[2];
+        assert!(!direct.enabled);
+        assert!(!direct.is_default);
+        assert_eq!(config.chain().count(), 2);
+    }
+
+    #[test]
+    fn imported_commands_need_confirmation() {
+        let mut config = FaviconServiceConfig::default();
+        config.services.push(FaviconService {
+            name: "Script".to_string(),
+            url_template: "fetch-icon {domain}".to_string(),
+            kind: ServiceKind::Command,
+            needs_confirmation: false,
+            ..FaviconService::direct()
+        });
+        config.services[3].enabled = true;
+        assert_eq!(config.require_command_confirmation(), 1);
+        assert!(!config.services[3].enabled);
+        assert!(config.services[3].needs_confirmation);
+
+        // 即使配置被手动改为启用，确认前也不参与获取
+        config.services[3].enabled = true;
+        assert!(config.chain().all(|service| service.kind != ServiceKind::Command));
+    }
+
+    #[test]
+    fn migrate_keeps_existing_direct_service() {
+        let mut config = FaviconServiceConfig::default();
+        config.migrate();
+        assert_eq!(config.services.iter().filter(|service| service.kind == ServiceKind::Direct).count(), 1);
+        assert!(config.services[2].enabled);
+        assert_eq!(config.services.iter().filter(|service| service.is_default).count(), 1);
+    }
+}
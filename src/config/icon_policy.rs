@@ -0,0 +1,40 @@
+//! 已有图标处理策略模块
+
+use serde::{Deserialize, Serialize};
+
+/// 书签已带有图标时的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IconPolicy {
+    /// 保留已有图标，只为没有图标的书签获取
+    Keep,
+    /// 总是用新获取的图标替换
+    #[default]
+    Replace,
+    /// 只替换空白、无法解码等占位图标
+    ReplacePlaceholder,
+}
+
+impl IconPolicy {
+    /// 所有策略，用于界面选择
+    pub const ALL: [IconPolicy; 3] = [IconPolicy::Keep, IconPolicy::Replace, IconPolicy::ReplacePlaceholder];
+
+    /// 策略名称的翻译键
+    pub fn message_key(self) -> &'static str {
+        match self {
+            IconPolicy::Keep => "icon_policy_keep",
+            IconPolicy::Replace => "icon_policy_replace",
+            IconPolicy::ReplacePlaceholder => "icon_policy_replace_placeholder",
+        }
+    }
+
+    /// 判断已有图标的书签是否需要重新获取
+    pub fn should_fetch(self, icon: Option<&str>) -> bool {
+        match (self, icon) {
+            (_, None) => true,
+            (IconPolicy::Keep, Some(_)) => false,
+            (IconPolicy::Replace, Some(_)) => true,
+            (IconPolicy::ReplacePlaceholder, Some(icon)) => crate::favicon::is_placeholder(icon),
+        }
+    }
+}
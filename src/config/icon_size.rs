@@ -0,0 +1,39 @@
+//! 图标尺寸模块
+
+use serde::{Deserialize, Serialize};
+
+/// 写入书签前把图标统一转换成的尺寸
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IconSize {
+    /// 保持获取到的原始图标
+    #[default]
+    Original,
+    /// 转换为 16×16 PNG
+    Px16,
+    /// 转换为 32×32 PNG
+    Px32,
+}
+
+impl IconSize {
+    /// 所有尺寸，用于界面选择
+    pub const ALL: [IconSize; 3] = [IconSize::Original, IconSize::Px16, IconSize::Px32];
+
+    /// 尺寸名称的翻译键
+    pub fn message_key(self) -> &'static str {
+        match self {
+            IconSize::Original => "icon_size_original",
+            IconSize::Px16 => "icon_size_16",
+            IconSize::Px32 => "icon_size_32",
+        }
+    }
+
+    /// 目标边长（像素），保持原始图标时返回 None
+    pub fn pixels(self) -> Option<u32> {
+        match self {
+            IconSize::Original => None,
+            IconSize::Px16 => Some(16),
+            IconSize::Px32 => Some(32),
+        }
+    }
+}
@@ -5,6 +5,8 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::errors::AppResult;
+use crate::utils::Recovery;
+use crate::favicon::Migration;
 use super::AppConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,19 +15,11 @@ pub struct ExportResult {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CacheData {
     pub favicon_urls: std::collections::HashMap<String, String>,
 }
 
-impl Default for CacheData {
-    fn default() -> Self {
-        Self {
-            favicon_urls: std::collections::HashMap::new(),
-        }
-    }
-}
-
 impl CacheData {
     /// 验证文件是否为有效的缓存数据格式
     pub fn validate_file(file_path: &Path) -> AppResult<()> {
@@ -77,6 +71,13 @@ impl CacheData {
     }
 }
 
+/// 缓存文件已从备份恢复或从另一种存储方式迁移时，在结果消息前加上说明
+fn with_notices(message: String, recovery: Option<Recovery>, migration: Option<Migration>) -> String {
+    let notices = recovery.map(|recovery| recovery.message()).into_iter()
+        .chain(migration.map(|migration| migration.message()));
+    notices.chain(std::iter::once(message)).collect::<Vec<_>>().join("\n")
+}
+
 /// 配置导入导出功能
 pub trait ConfigImportExport {
     /// 导出应用配置到文件
@@ -87,6 +88,8 @@ pub trait ConfigImportExport {
     fn export_cache(&self, file_path: &Path) -> AppResult<ExportResult>;
     /// 导入缓存数据
     fn import_cache(&mut self, file_path: &Path) -> AppResult<ExportResult>;
+    /// 从书签文件中已有的图标补充缓存
+    fn seed_cache(&mut self, file_path: &Path) -> AppResult<ExportResult>;
 }
 
 impl ConfigImportExport for AppConfig {
@@ -104,69 +107,33 @@ impl ConfigImportExport for AppConfig {
             return Err(crate::errors::AppError::FileNotFound(file_path.to_string()));
         }
         let content = fs::read_to_string(file_path)?;
-        let config = serde_json::from_str(&content)?;
+        let mut config: AppConfig = serde_json::from_str(&content)?;
+        config.favicon_service.migrate();
+        // 外部命令服务可以运行任意程序，导入后需在服务选项卡中确认
+        let mut message = crate::i18n::get_message("import_success", None);
+        let commands = config.favicon_service.require_command_confirmation();
+        if commands > 0 {
+            let mut args = HashMap::new();
+            args.insert("count".to_string(), commands.to_string());
+            message = format!("{}\n{}", message, crate::i18n::get_message("imported_commands_disabled", Some(args)));
+        }
         Ok((config, ExportResult {
             success: true,
-            message: crate::i18n::get_message("import_success", None),
+            message,
         }))
     }
 
     fn export_cache(&self, file_path: &Path) -> AppResult<ExportResult> {
-        // 获取缓存文件路径
-        let cache_path = crate::favicon::get_cache_path();
-
-        println!("导出缓存: 源路径 = {}, 目标路径 = {}", cache_path, file_path.display());
-
-        // 确保缓存文件存在
-        if !Path::new(&cache_path).exists() {
-            println!("警告: 缓存文件不存在: {}", cache_path);
-            // 创建一个空的缓存数据
-            let cache_data = CacheData {
-                favicon_urls: std::collections::HashMap::new(),
-            };
-
-            // 确保目标目录存在
-            if let Some(parent) = file_path.parent() {
-                println!("创建目标目录: {}", parent.display());
-                fs::create_dir_all(parent)?;
-            }
-
-            // 导出空的缓存数据
-            let content = serde_json::to_string_pretty(&cache_data)?;
-            println!("写入文件: {}", file_path.display());
-            fs::write(file_path, content)?;
-
-            return Ok(ExportResult {
-                success: true,
-                message: crate::i18n::get_message("cache_export_success", None),
-            });
-        }
-
         // 读取缓存数据
-        let favicon_cache = match fs::read_to_string(&cache_path) {
-            Ok(data) => {
-                println!("成功读取缓存文件, 大小: {} 字节", data.len());
-                match serde_json::from_str::<crate::favicon::FaviconCache>(&data) {
-                    Ok(cache) => {
-                        println!("成功解析缓存数据, 包含 {} 项", cache.0.len());
-                        cache.0
-                    },
-                    Err(e) => {
-                        println!("解析缓存数据失败: {}", e);
-                        std::collections::HashMap::new()
-                    },
-                }
-            },
-            Err(e) => {
-                println!("读取缓存文件失败: {}", e);
-                std::collections::HashMap::new()
-            },
-        };
+        let (mut store, migration) = crate::favicon::open_store(&self.cache)?;
+        let recovery = store.take_recovery();
+        let favicon_cache = store.entries()?;
 
         // 转换为导出格式
         let cache_data = CacheData {
             favicon_urls: favicon_cache.into_iter()
-                .filter_map(|(k, v)| v.map(|url| (k, url)))
+                .filter(|(_, v)| !v.is_generated())
+                .filter_map(|(k, v)| v.into_icon().map(|url| (k, url)))
                 .collect(),
         };
 
@@ -187,7 +154,7 @@ impl ConfigImportExport for AppConfig {
 
         Ok(ExportResult {
             success: true,
-            message: crate::i18n::get_message("cache_export_success", None),
+            message: with_notices(crate::i18n::get_message("cache_export_success", None), recovery, migration),
         })
     }
 
@@ -224,7 +191,8 @@ impl ConfigImportExport for AppConfig {
                     Ok(favicon_cache) => {
                         // 与 export_cache 中相同的转换逻辑
                         favicon_cache.0.into_iter()
-                            .filter_map(|(k, v)| v.map(|url| (k, url)))
+                            .filter(|(_, v)| !v.is_generated())
+                            .filter_map(|(k, v)| v.into_icon().map(|url| (k, url)))
                             .collect()
                     },
                     Err(_) => {
@@ -291,27 +259,40 @@ impl ConfigImportExport for AppConfig {
             }
         };
 
-        // 获取当前缓存
-        let cache_path = crate::favicon::get_cache_path();
-        let mut current_cache = if let Ok(data) = fs::read_to_string(&cache_path) {
-            serde_json::from_str::<crate::favicon::FaviconCache>(&data)
-                .unwrap_or_else(|_| crate::favicon::FaviconCache(HashMap::new()))
-        } else {
-            crate::favicon::FaviconCache(HashMap::new())
-        };
+        // 合并到当前缓存
+        let (mut store, migration) = crate::favicon::open_store(&self.cache)?;
+        let recovery = store.take_recovery();
+        store.put_all(favicon_urls.into_iter()
+            .map(|(domain, favicon)| (domain, crate::favicon::CacheEntry::imported(favicon)))
+            .collect())?;
+        store.flush()?;
+
+        Ok(ExportResult {
+            success: true,
+            message: with_notices(crate::i18n::get_message("cache_import_success", None), recovery, migration),
+        })
+    }
 
-        // 合并缓存数据
-        for (domain, favicon) in favicon_urls {
-            current_cache.0.insert(domain, Some(favicon));
+    fn seed_cache(&mut self, file_path: &Path) -> AppResult<ExportResult> {
+        if !file_path.exists() {
+            return Err(crate::errors::AppError::FileNotFound(file_path.display().to_string()));
         }
 
-        // 保存更新后的缓存
-        let updated_content = serde_json::to_string_pretty(&current_cache)?;
-        fs::write(cache_path, updated_content)?;
+        // 书签文件可以是任意支持的格式
+        let format = crate::bookmarks::BookmarkFormat::detect(file_path)?;
+        let tree = format.read(file_path)?;
+
+        // 合并到当前缓存
+        let (mut store, migration) = crate::favicon::open_store(&self.cache)?;
+        let recovery = store.take_recovery();
+        let seeded = store.seed_from_tree(&tree)?;
+        store.flush()?;
 
+        let mut args = HashMap::new();
+        args.insert("count".to_string(), seeded.to_string());
         Ok(ExportResult {
             success: true,
-            message: crate::i18n::get_message("cache_import_success", None),
+            message: with_notices(crate::i18n::get_message("cache_seed_success", Some(args)), recovery, migration),
         })
     }
 }
\ No newline at end of file
@@ -4,16 +4,25 @@
 
 pub mod favicon_service;
 mod language;
+mod icon_policy;
+mod icon_size;
+mod cache;
+mod network;
 pub mod import_export;
 
 pub use favicon_service::FaviconServiceConfig;
 pub use language::LanguageConfig;
+pub use icon_policy::IconPolicy;
+pub use icon_size::IconSize;
+pub use cache::{CacheBackend, CacheConfig};
+pub use network::NetworkConfig;
 pub use import_export::{ConfigImportExport, ExportResult};
 
 use std::fs;
 use std::path::Path;
 use serde::{Deserialize, Serialize};
 use crate::errors::AppResult;
+use crate::utils::{read_with_backup, write_atomic, Recovery};
 use std::io;
 
 /// 应用程序配置
@@ -25,6 +34,35 @@ pub struct AppConfig {
     /// 语言配置
     #[serde(flatten)]
     pub language: LanguageConfig,
+    /// 已有图标处理策略
+    #[serde(default)]
+    pub icon_policy: IconPolicy,
+    /// 写入书签前统一转换的图标尺寸
+    #[serde(default)]
+    pub icon_size: IconSize,
+    /// 处理时是否用条件请求校验所有可校验的缓存图标，而不只是已过期的
+    #[serde(default)]
+    pub refresh_cached_icons: bool,
+    /// 所有服务都失败时是否生成字母图标
+    #[serde(default = "default_generate_fallback_icons")]
+    pub generate_fallback_icons: bool,
+    /// 同时获取 favicon 的最大请求数
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// 网络请求配置
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// 缓存有效期配置
+    #[serde(default)]
+    pub cache: CacheConfig,
+}
+
+fn default_concurrency() -> usize {
+    8
+}
+
+fn default_generate_fallback_icons() -> bool {
+    false
 }
 
 impl Default for AppConfig {
@@ -32,6 +70,13 @@ impl Default for AppConfig {
         Self {
             favicon_service: FaviconServiceConfig::default(),
             language: LanguageConfig::default(),
+            icon_policy: IconPolicy::default(),
+            icon_size: IconSize::default(),
+            refresh_cached_icons: false,
+            generate_fallback_icons: default_generate_fallback_icons(),
+            concurrency: default_concurrency(),
+            network: NetworkConfig::default(),
+            cache: CacheConfig::default(),
         }
     }
 }
@@ -63,37 +108,33 @@ impl AppConfig {
         format!("{}/config.json", Self::get_app_dir())
     }
 
-    /// 加载配置
-    pub fn load() -> Self {
+    /// 加载配置，同时返回配置文件损坏时的处理结果
+    ///
+    /// 配置文件无法解析时改用 `.bak` 备份
+    pub fn load_with_recovery() -> (Self, Option<Recovery>) {
         let config_path = Self::get_config_path();
-        if Path::new(&config_path).exists() {
-            if let Ok(content) = fs::read_to_string(&config_path) {
-                if let Ok(config) = serde_json::from_str::<AppConfig>(&content) {
-                    return config;
-                }
-            }
+        let (config, recovery) = read_with_backup(Path::new(&config_path), |content| serde_json::from_str::<AppConfig>(content));
+        if let Some(mut config) = config {
+            config.favicon_service.migrate();
+            return (config, recovery);
         }
-        // 如果配置文件不存在或解析失败，返回默认配置
+        // 如果配置文件不存在且备份也不可用，返回默认配置
         let default_config = AppConfig::default();
         // 尝试保存默认配置
         let _ = default_config.save();
-        default_config
+        (default_config, recovery)
     }
 
     /// 保存配置
+    ///
+    /// 原子写入，上一次的配置保留为 `.bak` 备份
     pub fn save(&self) -> AppResult<()> {
         let config_path = Self::get_config_path();
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(&config_path, content)?;
+        write_atomic(&config_path, content)?;
         Ok(())
     }
 
-    /// 获取favicon URL
-    pub fn get_favicon_url(&self, domain: &str) -> String {
-        let service = &self.favicon_service.services[self.favicon_service.current_service_index];
-        service.url_template.replace("{domain}", domain)
-    }
-
     /// 导出服务配置到JSON文件
     pub fn export_services(&self, path: &Path) -> io::Result<()> {
         let json = serde_json::to_string_pretty(&self.favicon_service)?;
@@ -103,9 +144,11 @@ impl AppConfig {
     /// 从JSON文件导入服务配置
     pub fn import_services(&mut self, path: &Path) -> io::Result<()> {
         let json = fs::read_to_string(path)?;
-        let services: favicon_service::FaviconServiceConfig = serde_json::from_str(&json)?;
+        let mut services: favicon_service::FaviconServiceConfig = serde_json::from_str(&json)?;
+        services.migrate();
+        services.require_command_confirmation();
         self.favicon_service = services;
-        self.save().map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        self.save().map_err(io::Error::other)?;
         Ok(())
     }
 }
\ No newline at end of file
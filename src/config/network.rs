@@ -0,0 +1,43 @@
+//! 网络配置模块
+
+use serde::{Deserialize, Serialize};
+
+/// 网络请求配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// 连接超时（秒）
+    pub connect_timeout_secs: u64,
+    /// 读取超时（秒）
+    pub read_timeout_secs: u64,
+    /// 请求使用的 User-Agent
+    pub user_agent: String,
+    /// HTTP/HTTPS/SOCKS5 代理地址，如 `http://127.0.0.1:7890` 或 `socks5h://127.0.0.1:1080`
+    pub proxy: Option<String>,
+    /// 额外信任的 CA 证书文件（PEM 格式，可包含多个证书）
+    pub ca_bundle: Option<String>,
+    /// 是否接受无效的 TLS 证书，仅用于内网自签名证书
+    pub accept_invalid_certs: bool,
+    /// 临时性失败（超时、429、5xx、连接重置）的最大重试次数
+    pub max_retries: u32,
+    /// 首次重试前的等待时间（毫秒），之后每次加倍
+    pub retry_base_delay_ms: u64,
+    /// 单次重试最长等待时间（秒），同样限制 `Retry-After`
+    pub retry_max_delay_secs: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            proxy: None,
+            ca_bundle: None,
+            accept_invalid_certs: false,
+            max_retries: 2,
+            retry_base_delay_ms: 500,
+            retry_max_delay_secs: 30,
+        }
+    }
+}
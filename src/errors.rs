@@ -16,6 +16,13 @@ pub enum AppError {
     #[error("网络请求错误: {0}")]
     NetworkError(#[from] reqwest::Error),
 
+    /// favicon 获取失败，记录失败类型以决定是否重试
+    #[error("{message}")]
+    FetchError {
+        kind: crate::favicon::FailureKind,
+        message: String,
+    },
+
     /// URL 解析错误
     #[error("URL 解析错误: {0}")]
     UrlParseError(#[from] url::ParseError),
@@ -28,6 +35,10 @@ pub enum AppError {
     #[error("正则表达式错误: {0}")]
     RegexError(#[from] regex::Error),
 
+    /// 数据库错误
+    #[error("数据库错误: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+
     /// 图像处理错误
     #[error("图像处理错误: {0}")]
     ImageError(#[from] image::error::ImageError),
@@ -0,0 +1,43 @@
+//! 中止处理
+//!
+//! 用户中止时界面设置共享的中止标志，进行中的请求与该标志竞争，无需等待请求超时
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// 检查中止标志的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 等待中止标志被设置
+pub async fn aborted(abort_flag: &AtomicBool) {
+    while !abort_flag.load(Ordering::Relaxed) {
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// 运行 `future` 直到完成或被中止，中止时丢弃 `future` 并返回 None
+pub async fn until_aborted<F: Future>(abort_flag: &AtomicBool, future: F) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        _ = aborted(abort_flag) => None,
+        output = future => Some(output),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn returns_output_when_not_aborted() {
+        let abort_flag = AtomicBool::new(false);
+        assert_eq!(until_aborted(&abort_flag, async { 42 }).await, Some(42));
+    }
+
+    #[tokio::test]
+    async fn already_aborted_skips_future() {
+        let abort_flag = AtomicBool::new(true);
+        assert_eq!(until_aborted(&abort_flag, async { 42 }).await, None);
+    }
+}
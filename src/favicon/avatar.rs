@@ -0,0 +1,150 @@
+//! 字母图标生成
+//!
+//! 所有服务都获取失败时，用域名首字母和由域名哈希决定的背景色在本地画一个圆形图标
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::errors::AppResult;
+use super::icon::Icon;
+use super::normalize::encode_png;
+
+/// 先按该倍数放大绘制再缩小，得到平滑的边缘
+const SUPERSAMPLE: u32 = 4;
+
+/// 5×7 点阵字形，每行低 5 位从左到右表示像素
+const GLYPHS: [(char, [u8; 7]); 37] = [
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+];
+
+/// 图标上显示的字母：去掉 `www.` 和国际化域名前缀后的第一个字母或数字
+fn initial(domain: &str) -> char {
+    let name = domain.strip_prefix("www.").unwrap_or(domain);
+    let name = name.strip_prefix("xn--").unwrap_or(name);
+    name.chars()
+        .find(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .unwrap_or('?')
+}
+
+/// 由域名哈希决定的背景色，饱和度和亮度固定，保证白色字母清晰可见
+fn background(domain: &str) -> Rgba<u8> {
+    let digest = md5::compute(domain.as_bytes());
+    let hue = u16::from_be_bytes([digest[0], digest[1]]) as f32 / 65536.0 * 360.0;
+    let (saturation, lightness) = (0.55, 0.45);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f32).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round() as u8;
+    Rgba([channel(r), channel(g), channel(b), 255])
+}
+
+/// 生成 `size`×`size` 的字母图标，同一域名总是得到相同的图标
+pub fn generate(domain: &str, size: u32) -> AppResult<Icon> {
+    let glyph = GLYPHS.iter()
+        .find(|(c, _)| *c == initial(domain))
+        .map(|(_, rows)| rows)
+        .unwrap_or(&GLYPHS[GLYPHS.len() - 1].1);
+    let color = background(domain);
+
+    let canvas = size * SUPERSAMPLE;
+    let radius = canvas as f32 / 2.0;
+    // 字形高度为图标的一半，居中放置
+    let cell = canvas as f32 * 0.5 / 7.0;
+    let left = (canvas as f32 - cell * 5.0) / 2.0;
+    let top = (canvas as f32 - cell * 7.0) / 2.0;
+    let image = RgbaImage::from_fn(canvas, canvas, |x, y| {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        if (px - radius).powi(2) + (py - radius).powi(2) > radius * radius {
+            return Rgba([0, 0, 0, 0]);
+        }
+        let (column, row) = (((px - left) / cell).floor(), ((py - top) / cell).floor());
+        let lit = (0.0..5.0).contains(&column) && (0.0..7.0).contains(&row)
+            && glyph[row as usize] & (0x10 >> column as u32) != 0;
+        if lit { Rgba([255, 255, 255, 255]) } else { color }
+    });
+    let image = image::imageops::resize(&image, size, size, FilterType::Triangle);
+    Ok(Icon::new("image/png", encode_png(DynamicImage::ImageRgba8(image))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_skips_www_and_punycode_prefixes() {
+        assert_eq!(initial("example.com"), 'E');
+        assert_eq!(initial("www.github.com"), 'G');
+        assert_eq!(initial("xn--fiqs8s.cn"), 'F');
+        assert_eq!(initial("www.xn--80ak6aa92e.com"), '8');
+        assert_eq!(initial("_dmarc.example.com"), 'D');
+        assert_eq!(initial("wwwexample.com"), 'W');
+        assert_eq!(initial("--.."), '?');
+    }
+
+    #[test]
+    fn same_domain_gives_same_icon() {
+        let icon = generate("example.com", 32).unwrap();
+        assert_eq!(generate("example.com", 32).unwrap(), icon);
+        assert_ne!(generate("example.org", 32).unwrap(), icon);
+        assert_eq!(background("example.com"), background("example.com"));
+        assert_ne!(background("example.com"), background("example.org"));
+    }
+
+    #[test]
+    fn icon_has_requested_size() {
+        for size in [16, 32, 64] {
+            let icon = generate("example.com", size).unwrap();
+            assert_eq!(icon.mime, "image/png");
+            let image = image::load_from_memory(&icon.data).unwrap().to_rgba8();
+            assert_eq!(image.dimensions(), (size, size));
+            // 圆形以外透明，中心是白色字母或背景色
+            assert_eq!(image.get_pixel(0, 0).0[3], 0);
+            assert_eq!(image.get_pixel(size / 2, size / 2).0[3], 255);
+        }
+    }
+}
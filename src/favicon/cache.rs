@@ -1,13 +1,290 @@
 use std::collections::HashMap;
+use std::path::Path;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
-use crate::config::AppConfig;
+use crate::config::{AppConfig, CacheConfig};
+use crate::errors::AppResult;
+use crate::utils::{data_url, read_with_backup, write_atomic, Recovery};
+use super::fetch::{FailureKind, Validators};
+use super::icon::Icon;
+use super::placeholder::content_hash;
 
-/// Favicon缓存结构：key为域名，value为Option<String>（base64或None）
+/// 缓存中保存图标数据使用的键，格式为 `sha256:<十六进制>`
+///
+/// 内容相同的图标共用一份数据，键必须足以区分不同的内容，因此不使用 MD5 的 [`content_hash`]
+pub(crate) fn blob_hash(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+/// 当前缓存文件版本
+pub const CACHE_VERSION: u32 = 1;
+
+/// 缓存条目
+///
+/// 获取成功时记录图标及其来源、类型、大小、哈希和 HTTP 校验信息，失败时记录失败类型和原因
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// 获取或导入的时间（Unix 秒）
+    pub fetched_at: i64,
+    /// 图标 data URL，失败时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// 获取成功的服务名称，导入的图标没有来源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// 图标 MIME 类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime: Option<String>,
+    /// 图标图片数据的字节数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// 图标图片数据的内容哈希，格式为 `md5:<十六进制>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// 图标的下载地址，用于条件请求
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 响应中的 `ETag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// 响应中的 `Last-Modified`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// 获取失败的类型
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureKind>,
+    /// 获取失败的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<String>,
+    /// 是否为所有服务都失败时本地生成的字母图标
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
+}
+
+impl CacheEntry {
+    fn new(fetched_at: i64) -> Self {
+        Self {
+            fetched_at,
+            icon: None,
+            provider: None,
+            mime: None,
+            size: None,
+            hash: None,
+            url: None,
+            etag: None,
+            last_modified: None,
+            failure: None,
+            failure_reason: None,
+            generated: false,
+        }
+    }
+
+    fn with_icon(icon: &Icon) -> Self {
+        Self {
+            icon: Some(icon.to_data_url()),
+            mime: Some(icon.mime.clone()),
+            size: Some(icon.data.len()),
+            hash: Some(content_hash(&icon.data)),
+            url: icon.url.clone(),
+            etag: icon.etag.clone(),
+            last_modified: icon.last_modified.clone(),
+            ..Self::new(Utc::now().timestamp())
+        }
+    }
+
+    /// 由某个服务获取成功的图标
+    pub fn fetched(icon: &Icon, provider: impl Into<String>) -> Self {
+        Self {
+            provider: Some(provider.into()),
+            ..Self::with_icon(icon)
+        }
+    }
+
+    /// 所有服务都失败时本地生成的字母图标
+    pub fn generated(icon: &Icon, failure: FailureKind, reason: impl Into<String>) -> Self {
+        Self {
+            failure: Some(failure),
+            failure_reason: Some(reason.into()),
+            generated: true,
+            ..Self::with_icon(icon)
+        }
+    }
+
+    /// 来源未知的图标（导入的缓存或书签中已有的图标），值为 base64 data URL
+    pub fn imported(data_url: impl Into<String>) -> Self {
+        let data_url = data_url.into();
+        let mut entry = Self::new(Utc::now().timestamp());
+        if let Some((mime, data)) = crate::utils::data_url::decode(&data_url) {
+            entry.mime = Some(mime);
+            entry.size = Some(data.len());
+            entry.hash = Some(content_hash(&data));
+        }
+        entry.icon = Some(data_url);
+        entry
+    }
+
+    /// 获取失败
+    pub fn failed(failure: FailureKind, reason: impl Into<String>) -> Self {
+        Self {
+            failure: Some(failure),
+            failure_reason: Some(reason.into()),
+            ..Self::new(Utc::now().timestamp())
+        }
+    }
+
+    /// 图标 data URL，失败时为 None
+    pub fn icon(&self) -> Option<&String> {
+        self.icon.as_ref()
+    }
+
+    /// 取出图标 data URL，失败时为 None
+    pub fn into_icon(self) -> Option<String> {
+        self.icon
+    }
+
+    /// 获取成功的服务名称
+    pub fn provider(&self) -> Option<&str> {
+        self.provider.as_deref()
+    }
+
+    /// 是否为本地生成的图标
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+
+    /// 发送条件请求所需的图标地址和校验信息
+    ///
+    /// 生成的图标、没有下载地址或响应中没有 `ETag` 和 `Last-Modified` 的图标无法校验，返回 None
+    pub fn revalidation(&self) -> Option<(&str, Validators)> {
+        let validators = Validators { etag: self.etag.clone(), last_modified: self.last_modified.clone() };
+        match (&self.icon, &self.url) {
+            (Some(_), Some(url)) if !self.generated && !validators.is_empty() => Some((url.as_str(), validators)),
+            _ => None,
+        }
+    }
+
+    /// 是否在有效期内，可以直接使用而不必重新获取
+    ///
+    /// 获取到的图标和永久性失败分别按配置的有效期判断，生成的图标按记录的失败类型处理；
+    /// 临时性失败在下次处理时重新获取
+    pub fn is_fresh(&self, ttl: &CacheConfig, now: i64) -> bool {
+        let ttl_days = match (&self.icon, self.failure) {
+            (Some(_), _) if !self.generated => ttl.positive_ttl_days,
+            (_, Some(failure)) if !failure.is_transient() => ttl.negative_ttl_days,
+            _ => return false,
+        };
+        ttl_days == 0 || now.saturating_sub(self.fetched_at) < (ttl_days as i64).saturating_mul(86_400)
+    }
+}
+
+/// 缓存文件中的条目
+#[derive(Serialize, Deserialize)]
+struct StoredEntry {
+    #[serde(flatten)]
+    entry: CacheEntry,
+    /// 图标在 `icons` 中的内容哈希，此时条目本身不含图标；无法解码的图标仍直接保存在条目中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_ref: Option<String>,
+}
+
+/// 缓存文件内容
+///
+/// 相同的图标只在 `icons` 中按内容哈希保存一次 base64 数据，各域名的条目引用其哈希
 #[derive(Serialize, Deserialize)]
-pub struct FaviconCache(pub HashMap<String, Option<String>>);
+struct CacheFile {
+    version: u32,
+    #[serde(default)]
+    icons: HashMap<String, String>,
+    entries: HashMap<String, StoredEntry>,
+}
+
+/// 读取时兼容的缓存文件格式
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyCacheFile {
+    Versioned(CacheFile),
+    /// 旧版本的域名到 data URL 的映射，获取失败为 `null`
+    Legacy(HashMap<String, Option<String>>),
+}
+
+impl From<AnyCacheFile> for FaviconCache {
+    fn from(file: AnyCacheFile) -> Self {
+        match file {
+            AnyCacheFile::Versioned(CacheFile { icons, entries, .. }) => FaviconCache(entries.into_iter()
+                .map(|(domain, StoredEntry { mut entry, icon_ref })| {
+                    if let Some(b64) = icon_ref.and_then(|key| icons.get(&key)) {
+                        entry.icon = Some(data_url::from_base64(entry.mime.as_deref().unwrap_or_default(), b64));
+                    }
+                    (domain, entry)
+                })
+                .collect()),
+            // 旧版本没有获取时间，图标按迁移时获取处理；失败记为已过期，下次处理时重新获取
+            AnyCacheFile::Legacy(entries) => FaviconCache(entries.into_iter()
+                .map(|(domain, icon)| {
+                    let entry = match icon {
+                        Some(icon) => CacheEntry::imported(icon),
+                        None => CacheEntry { failure: Some(FailureKind::Other), ..CacheEntry::new(0) },
+                    };
+                    (domain, entry)
+                })
+                .collect()),
+        }
+    }
+}
+
+impl From<FaviconCache> for CacheFile {
+    /// base64 data URL 图标移入 `icons`，无法解码的图标仍保存在条目中
+    fn from(cache: FaviconCache) -> Self {
+        let mut icons = HashMap::new();
+        let entries = cache.0.into_iter()
+            .map(|(domain, mut entry)| {
+                let icon_ref = entry.icon.as_deref()
+                    .and_then(|icon| Some((data_url::decode(icon)?, icon.split_once(',')?.1.trim())))
+                    .map(|((mime, data), b64)| {
+                        let key = blob_hash(&data);
+                        icons.entry(key.clone()).or_insert_with(|| b64.to_string());
+                        entry.mime = Some(mime);
+                        key
+                    });
+                if icon_ref.is_some() {
+                    entry.icon = None;
+                }
+                (domain, StoredEntry { entry, icon_ref })
+            })
+            .collect();
+        CacheFile { version: CACHE_VERSION, icons, entries }
+    }
+}
 
-/// 获取缓存文件路径
+/// Favicon缓存结构：key为域名，value为缓存条目
+///
+/// JSON 存储和导入导出使用的格式。保存为带版本号的 `{"version": 1, "icons": {...}, "entries": {...}}`，
+/// 读取时兼容旧版本的域名到 data URL 的映射
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "AnyCacheFile", into = "CacheFile")]
+pub struct FaviconCache(pub HashMap<String, CacheEntry>);
+
+impl FaviconCache {
+    /// 从磁盘加载缓存，同时返回缓存文件损坏时的处理结果
+    ///
+    /// 文件无法解析时改用 `.bak` 备份，文件不存在或备份也不可用时返回空缓存
+    pub fn load(cache_path: &str) -> (Self, Option<Recovery>) {
+        let (cache, recovery) = read_with_backup(Path::new(cache_path), |content| serde_json::from_str::<FaviconCache>(content));
+        (cache.unwrap_or_else(|| FaviconCache(HashMap::new())), recovery)
+    }
+
+    /// 保存缓存到磁盘
+    ///
+    /// 原子写入，上一次的缓存保留为 `.bak` 备份
+    pub fn save(&self, cache_path: &str) -> AppResult<()> {
+        write_atomic(cache_path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// 获取 JSON 缓存文件路径
 pub fn get_cache_path() -> String {
     #[cfg(target_os = "windows")]
     {
@@ -18,4 +295,104 @@ pub fn get_cache_path() -> String {
     {
         format!("{}/favicon_cache.json", AppConfig::get_app_dir())
     }
-}
\ No newline at end of file
+}
+
+/// 获取 SQLite 缓存数据库路径
+pub fn get_cache_db_path() -> String {
+    #[cfg(target_os = "windows")]
+    {
+        format!("{}\\favicon_cache.sqlite", AppConfig::get_app_dir())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        format!("{}/favicon_cache.sqlite", AppConfig::get_app_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn ttl() -> CacheConfig {
+        CacheConfig { positive_ttl_days: 30, negative_ttl_days: 7, ..CacheConfig::default() }
+    }
+
+    fn icon() -> Icon {
+        Icon::new("image/png", vec![1, 2, 3])
+    }
+
+    fn at(entry: CacheEntry, fetched_at: i64) -> CacheEntry {
+        CacheEntry { fetched_at, ..entry }
+    }
+
+    #[test]
+    fn fetched_icons_use_positive_ttl() {
+        let entry = at(CacheEntry::fetched(&icon(), "Google"), 0);
+        assert!(entry.is_fresh(&ttl(), 29 * DAY));
+        assert!(!entry.is_fresh(&ttl(), 30 * DAY));
+    }
+
+    #[test]
+    fn failures_use_negative_ttl_unless_transient() {
+        let permanent = at(CacheEntry::failed(FailureKind::NotFound, "HTTP 404"), 0);
+        assert!(permanent.is_fresh(&ttl(), 6 * DAY));
+        assert!(!permanent.is_fresh(&ttl(), 7 * DAY));
+        let transient = at(CacheEntry::failed(FailureKind::Timeout, "timed out"), 0);
+        assert!(!transient.is_fresh(&ttl(), 0));
+    }
+
+    #[test]
+    fn generated_icons_follow_their_failure() {
+        let permanent = at(CacheEntry::generated(&icon(), FailureKind::NotFound, "HTTP 404"), 0);
+        assert!(permanent.is_fresh(&ttl(), 6 * DAY));
+        assert!(!permanent.is_fresh(&ttl(), 7 * DAY));
+        let transient = at(CacheEntry::generated(&icon(), FailureKind::ServerError, "HTTP 503"), 0);
+        assert!(!transient.is_fresh(&ttl(), 0));
+        // 没有记录失败类型的生成图标
+        let legacy = CacheEntry { failure: None, ..permanent };
+        assert!(!legacy.is_fresh(&ttl(), 0));
+    }
+
+    #[test]
+    fn legacy_cache_is_converted() {
+        let icon = icon().to_data_url();
+        let cache: FaviconCache = serde_json::from_str(&format!(r#"{{"a.test": "{}", "b.test": null}}"#, icon)).unwrap();
+        let a = &cache.0["a.test"];
+        assert_eq!(a.icon(), Some(&icon));
+        assert_eq!(a.hash.as_deref(), Some(content_hash(&[1, 2, 3]).as_str()));
+        assert!(a.is_fresh(&ttl(), Utc::now().timestamp()));
+        let b = &cache.0["b.test"];
+        assert!(b.icon().is_none());
+        assert!(!b.is_fresh(&ttl(), Utc::now().timestamp()));
+    }
+
+    #[test]
+    fn identical_icons_are_stored_once_by_sha256() {
+        let mut cache = FaviconCache(HashMap::new());
+        for domain in ["a.test", "b.test"] {
+            cache.0.insert(domain.to_string(), CacheEntry::fetched(&icon(), "Google"));
+        }
+        cache.0.insert("c.test".to_string(), CacheEntry::fetched(&Icon::new("image/png", vec![4, 5, 6]), "Google"));
+        let json: serde_json::Value = serde_json::to_value(&cache).unwrap();
+        assert_eq!(json["version"], CACHE_VERSION);
+        let icons = json["icons"].as_object().unwrap();
+        assert_eq!(icons.len(), 2);
+        let key = blob_hash(&[1, 2, 3]);
+        assert!(icons.contains_key(&key));
+        assert_eq!(json["entries"]["a.test"]["icon_ref"], key.as_str());
+        assert!(json["entries"]["a.test"].get("icon").is_none());
+
+        let loaded: FaviconCache = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.0, cache.0);
+    }
+
+    #[test]
+    fn zero_ttl_never_expires() {
+        let ttl = CacheConfig { positive_ttl_days: 0, negative_ttl_days: 0, ..CacheConfig::default() };
+        assert!(at(CacheEntry::fetched(&icon(), "Google"), 0).is_fresh(&ttl, 1000 * DAY));
+        assert!(at(CacheEntry::generated(&icon(), FailureKind::NotFound, "HTTP 404"), 0).is_fresh(&ttl, 1000 * DAY));
+    }
+}
@@ -0,0 +1,59 @@
+//! HTTP 客户端
+//!
+//! 根据 [`NetworkConfig`] 创建 reqwest 客户端，一次处理中的所有请求共用同一个客户端
+
+use std::fs;
+use std::time::Duration;
+
+use reqwest::{Certificate, Proxy};
+
+use crate::config::NetworkConfig;
+use crate::errors::AppResult;
+
+/// 创建异步 HTTP 客户端
+pub fn build_client(config: &NetworkConfig) -> AppResult<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.read_timeout_secs))
+        .user_agent(&config.user_agent)
+        .danger_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(proxy) = proxy(config)? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder.build()?)
+}
+
+/// 创建同步 HTTP 客户端
+///
+/// 同步客户端不区分读取超时，以读取超时作为整个请求的超时
+pub fn build_blocking_client(config: &NetworkConfig) -> AppResult<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_secs))
+        .timeout(Duration::from_secs(config.read_timeout_secs))
+        .user_agent(&config.user_agent)
+        .danger_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(proxy) = proxy(config)? {
+        builder = builder.proxy(proxy);
+    }
+    for certificate in certificates(config)? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    Ok(builder.build()?)
+}
+
+fn proxy(config: &NetworkConfig) -> AppResult<Option<Proxy>> {
+    match config.proxy.as_deref().map(str::trim) {
+        Some(url) if !url.is_empty() => Ok(Some(Proxy::all(url)?)),
+        _ => Ok(None),
+    }
+}
+
+fn certificates(config: &NetworkConfig) -> AppResult<Vec<Certificate>> {
+    match config.ca_bundle.as_deref().map(str::trim) {
+        Some(path) if !path.is_empty() => Ok(Certificate::from_pem_bundle(&fs::read(path)?)?),
+        _ => Ok(Vec::new()),
+    }
+}
@@ -0,0 +1,371 @@
+//! 直接获取 favicon
+//!
+//! 不经过第三方服务，直接请求书签页面，从 `<link rel="icon">`、`apple-touch-icon`、`mask-icon`
+//! 和 `<meta>` 标签中找出图标地址，`<link rel="manifest">` 指向的 Web 应用清单中的图标也一并参与选择，
+//! 都没有或都获取失败时再尝试站点根目录的 `/favicon.ico`
+
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use url::Url;
+
+use crate::config::NetworkConfig;
+use crate::errors::{AppError, AppResult};
+use super::fetch::fetch_icon_async;
+use super::icon::Icon;
+
+/// 偏好的图标尺寸
+const PREFERRED_SIZE: u32 = 32;
+
+/// 页面中声明的图标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconCandidate {
+    /// 解析后的绝对地址
+    pub url: Url,
+    /// 排序用的优先级，越小越优先
+    pub rank: u32,
+}
+
+/// 页面中解析出的图标声明
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageIcons {
+    /// `<link>` 和 `<meta>` 声明的图标
+    pub icons: Vec<IconCandidate>,
+    /// `<link rel="manifest">` 指向的 Web 应用清单
+    pub manifest: Option<Url>,
+}
+
+/// Web 应用清单，只读取图标部分
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    icons: Vec<ManifestIcon>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestIcon {
+    src: String,
+    #[serde(default)]
+    sizes: Option<String>,
+    #[serde(rename = "type", default)]
+    mime: Option<String>,
+    #[serde(default)]
+    purpose: Option<String>,
+}
+
+/// 获取页面并解析其中声明的图标，包括 Web 应用清单中的图标，按优先级排序
+///
+/// 相对地址按 `<base href>` 或重定向后的页面地址解析，清单中的地址按清单地址解析
+pub async fn discover_icons(client: &reqwest::Client, page_url: &str) -> AppResult<(Url, Vec<IconCandidate>)> {
+    let resp = client.get(page_url).send().await?;
+    let final_url = resp.url().clone();
+    if !resp.status().is_success() {
+        return Ok((final_url, Vec::new()));
+    }
+    let is_html = resp.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_none_or(|v| v.contains("html"));
+    if !is_html {
+        return Ok((final_url, Vec::new()));
+    }
+    let html = resp.text().await?;
+    let page = parse_page(&html, &final_url);
+    let mut candidates = page.icons;
+    if let Some(manifest_url) = page.manifest {
+        // 清单获取失败不影响页面中声明的图标
+        if let Ok(icons) = fetch_manifest_icons(client, &manifest_url).await {
+            candidates.extend(icons);
+        }
+    }
+    sort_candidates(&mut candidates);
+    Ok((final_url, candidates))
+}
+
+/// 从 HTML 中解析图标声明和 Web 应用清单地址
+pub fn parse_page(html: &str, page_url: &Url) -> PageIcons {
+    let document = Html::parse_document(html);
+    let base_url = Selector::parse("base[href]").ok()
+        .and_then(|selector| document.select(&selector).next())
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| page_url.join(href.trim()).ok())
+        .unwrap_or_else(|| page_url.clone());
+
+    let mut candidates = Vec::new();
+    let mut manifest = None;
+    if let Ok(selector) = Selector::parse("link[rel][href]") {
+        for link in document.select(&selector) {
+            let element = link.value();
+            let rel = element.attr("rel").unwrap_or_default().to_ascii_lowercase();
+            let rels: Vec<&str> = rel.split_ascii_whitespace().collect();
+            if rels.contains(&"manifest") {
+                if manifest.is_none() {
+                    manifest = element.attr("href").and_then(|href| base_url.join(href.trim()).ok());
+                }
+                continue;
+            }
+            let base_rank = if rels.contains(&"icon") {
+                0
+            } else if rels.contains(&"apple-touch-icon") || rels.contains(&"apple-touch-icon-precomposed") {
+                100
+            } else if rels.contains(&"mask-icon") {
+                300
+            } else {
+                continue;
+            };
+            let Some(url) = element.attr("href").and_then(|href| base_url.join(href.trim()).ok()) else {
+                continue;
+            };
+            let rank = base_rank + size_penalty(element.attr("sizes"));
+            candidates.push(IconCandidate { url, rank });
+        }
+    }
+    if let Ok(selector) = Selector::parse("meta[content]") {
+        for meta in document.select(&selector) {
+            let element = meta.value();
+            let name = element.attr("name").or_else(|| element.attr("itemprop")).unwrap_or_default().to_ascii_lowercase();
+            let rank = match name.as_str() {
+                "msapplication-tileimage" | "msapplication-square70x70logo" => 200,
+                "msapplication-square150x150logo" => 210,
+                "image" => 250,
+                _ => continue,
+            };
+            if let Some(url) = element.attr("content").and_then(|content| base_url.join(content.trim()).ok()) {
+                candidates.push(IconCandidate { url, rank });
+            }
+        }
+    }
+    sort_candidates(&mut candidates);
+    PageIcons { icons: candidates, manifest }
+}
+
+/// 按优先级排序并去除重复地址
+fn sort_candidates(candidates: &mut Vec<IconCandidate>) {
+    candidates.sort_by_key(|candidate| candidate.rank);
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.url.clone()));
+}
+
+/// 获取 Web 应用清单并解析其中的图标
+pub async fn fetch_manifest_icons(client: &reqwest::Client, manifest_url: &Url) -> AppResult<Vec<IconCandidate>> {
+    let resp = client.get(manifest_url.as_str()).send().await?.error_for_status()?;
+    let content = resp.text().await?;
+    Ok(parse_manifest(&content, manifest_url)?)
+}
+
+/// 解析 Web 应用清单中的图标
+///
+/// 清单图标一般是 192px 以上的应用图标，排在页面声明的 `icon` 之后、`apple-touch-icon` 之前；
+/// 同时按 `sizes`、`type` 和 `purpose` 调整优先级，只用于 `maskable` 或 `monochrome` 的图标排在最后
+pub fn parse_manifest(content: &str, manifest_url: &Url) -> serde_json::Result<Vec<IconCandidate>> {
+    let manifest: Manifest = serde_json::from_str(content)?;
+    Ok(manifest.icons.into_iter()
+        .filter_map(|icon| {
+            let url = manifest_url.join(icon.src.trim()).ok()?;
+            let rank = 50 + size_penalty(icon.sizes.as_deref()) + type_penalty(icon.mime.as_deref()) + purpose_penalty(icon.purpose.as_deref());
+            Some(IconCandidate { url, rank })
+        })
+        .collect())
+}
+
+/// 根据 MIME 类型调整优先级，SVG 图标部分浏览器导入时不支持
+fn type_penalty(mime: Option<&str>) -> u32 {
+    match mime.map(|m| m.trim().to_ascii_lowercase()).as_deref() {
+        Some("image/png") | Some("image/x-icon") | Some("image/vnd.microsoft.icon") => 0,
+        Some("image/svg+xml") => 40,
+        _ => 5,
+    }
+}
+
+/// 根据 `purpose` 调整优先级，未指定时等同于 `any`
+fn purpose_penalty(purpose: Option<&str>) -> u32 {
+    let Some(purpose) = purpose else {
+        return 0;
+    };
+    let purposes: Vec<String> = purpose.split_ascii_whitespace().map(|p| p.to_ascii_lowercase()).collect();
+    if purposes.is_empty() || purposes.iter().any(|p| p == "any") {
+        0
+    } else if purposes.iter().any(|p| p == "maskable") {
+        200
+    } else {
+        300
+    }
+}
+
+/// 根据 `sizes` 属性计算与偏好尺寸的差距，`any`（矢量图）视为合适尺寸
+fn size_penalty(sizes: Option<&str>) -> u32 {
+    let Some(sizes) = sizes else {
+        return 20;
+    };
+    sizes.split_ascii_whitespace()
+        .filter_map(|size| {
+            if size.eq_ignore_ascii_case("any") {
+                return Some(0);
+            }
+            let size = size.to_ascii_lowercase();
+            let (width, _) = size.split_once('x')?;
+            let width: u32 = width.parse().ok()?;
+            // 小于偏好尺寸的图标放大后模糊，比大图标扣分更多
+            Some(if width >= PREFERRED_SIZE { (width - PREFERRED_SIZE) / 16 } else { (PREFERRED_SIZE - width) * 2 })
+        })
+        .min()
+        .unwrap_or(20)
+        .min(90)
+}
+
+/// 直接从站点获取 favicon
+///
+/// 依次尝试页面声明的图标和 `/favicon.ico`，返回第一个获取成功的图标
+pub async fn fetch_direct(client: &reqwest::Client, page_url: &str, config: &NetworkConfig) -> AppResult<Icon> {
+    let (final_url, candidates) = match discover_icons(client, page_url).await {
+        Ok(result) => result,
+        Err(_) => (Url::parse(page_url)?, Vec::new()),
+    };
+    let mut urls: Vec<Url> = candidates.into_iter().map(|candidate| candidate.url).collect();
+    if let Ok(fallback) = final_url.join("/favicon.ico") {
+        if !urls.contains(&fallback) {
+            urls.push(fallback);
+        }
+    }
+
+    let mut last_error = AppError::CustomError(format!("No favicon found for {}", page_url));
+    for url in urls {
+        // 页面中内嵌的 data URL 图标直接使用
+        if url.scheme() == "data" {
+            if let Some(icon) = Icon::from_data_url(url.as_str()) {
+                return Ok(icon);
+            }
+            continue;
+        }
+        match fetch_icon_async(client, url.as_str(), config).await {
+            Ok(icon) => return Ok(icon),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::favicon::test_server::{self, Response, TestServer};
+
+    fn page_url() -> Url {
+        Url::parse("https://example.com/docs/page.html").unwrap()
+    }
+
+    fn urls(candidates: &[IconCandidate]) -> Vec<&str> {
+        candidates.iter().map(|candidate| candidate.url.as_str()).collect()
+    }
+
+    #[test]
+    fn relative_urls_use_base_href() {
+        let html = r#"<html><head>
+            <base href="/static/">
+            <link rel="icon" href="favicon.png">
+            <link rel="manifest" href="site.webmanifest">
+        </head></html>"#;
+        let page = parse_page(html, &page_url());
+        assert_eq!(urls(&page.icons), ["https://example.com/static/favicon.png"]);
+        assert_eq!(page.manifest.unwrap().as_str(), "https://example.com/static/site.webmanifest");
+
+        // 没有 <base> 时按页面地址解析
+        let page = parse_page(r#"<link rel="icon" href="favicon.png">"#, &page_url());
+        assert_eq!(urls(&page.icons), ["https://example.com/docs/favicon.png"]);
+    }
+
+    #[test]
+    fn rel_is_matched_case_insensitively() {
+        let html = r#"<link rel="Shortcut ICON" href="/a.ico"><link REL="Apple-Touch-Icon" href="/b.png"><link rel="stylesheet" href="/c.css">"#;
+        let page = parse_page(html, &page_url());
+        assert_eq!(urls(&page.icons), ["https://example.com/a.ico", "https://example.com/b.png"]);
+    }
+
+    #[test]
+    fn candidates_are_ranked_by_kind_and_size() {
+        let html = r#"<html><head>
+            <meta name="msapplication-TileImage" content="/tile.png">
+            <link rel="mask-icon" href="/mask.svg">
+            <meta itemprop="image" content="/og.png">
+            <link rel="apple-touch-icon" href="/touch.png">
+            <link rel="icon" sizes="16x16" href="/16.png">
+            <link rel="icon" sizes="32x32" href="/32.png">
+            <link rel="icon" sizes="192x192" href="/192.png">
+            <link rel="icon" href="/32.png">
+        </head></html>"#;
+        let page = parse_page(html, &page_url());
+        assert_eq!(urls(&page.icons), [
+            "https://example.com/32.png",
+            "https://example.com/192.png",
+            "https://example.com/16.png",
+            "https://example.com/touch.png",
+            "https://example.com/tile.png",
+            "https://example.com/og.png",
+            "https://example.com/mask.svg",
+        ]);
+        assert!(page.manifest.is_none());
+    }
+
+    #[test]
+    fn manifest_icons_are_ranked_by_size_type_and_purpose() {
+        let manifest = r#"{
+            "name": "Example",
+            "icons": [
+                { "src": "icons/mono.png", "sizes": "48x48", "type": "image/png", "purpose": "monochrome" },
+                { "src": "icons/mask.png", "sizes": "512x512", "type": "image/png", "purpose": "maskable" },
+                { "src": "icons/any.svg", "sizes": "any", "type": "image/svg+xml" },
+                { "src": "https://cdn.example.net/icon.png" },
+                { "src": "icons/192.png", "sizes": "192x192", "type": "image/png" },
+                { "src": " /static/48.png ", "sizes": "48x48", "type": "image/png", "purpose": "maskable any" }
+            ]
+        }"#;
+        let manifest_url = Url::parse("https://example.com/app/manifest.json").unwrap();
+        let mut icons = parse_manifest(manifest, &manifest_url).unwrap();
+        sort_candidates(&mut icons);
+        assert_eq!(urls(&icons), [
+            "https://example.com/static/48.png",
+            "https://example.com/app/icons/192.png",
+            "https://cdn.example.net/icon.png",
+            "https://example.com/app/icons/any.svg",
+            "https://example.com/app/icons/mask.png",
+            "https://example.com/app/icons/mono.png",
+        ]);
+
+        // 普通的清单图标排在页面声明的 icon 之后、apple-touch-icon 之前
+        let page = parse_page(r#"<link rel="icon" href="/favicon.png"><link rel="apple-touch-icon" href="/touch.png">"#, &page_url());
+        assert!(page.icons[0].rank < icons[0].rank && icons[1].rank < page.icons[1].rank);
+    }
+
+    #[test]
+    fn manifest_without_icons_is_empty() {
+        let manifest_url = Url::parse("https://example.com/manifest.json").unwrap();
+        assert!(parse_manifest(r#"{ "name": "Example" }"#, &manifest_url).unwrap().is_empty());
+        assert!(parse_manifest("not json", &manifest_url).is_err());
+    }
+
+    #[tokio::test]
+    async fn declared_icons_are_tried_before_favicon_ico() {
+        let html = r#"<link rel="apple-touch-icon" href="/touch.png"><link rel="icon" href="/missing.png">"#;
+        let server = TestServer::start(vec![
+            Response::new("200 OK").header("Content-Type", "text/html").body(html),
+            Response::new("404 Not Found"),
+            Response::new("404 Not Found"),
+            Response::png(),
+        ]).await;
+        let page = server.url.trim_end_matches("favicon.ico");
+        let icon = fetch_direct(&test_server::client(), page, &NetworkConfig::default()).await.unwrap();
+        assert_eq!(icon.data, test_server::png());
+
+        let paths: Vec<String> = server.requests().iter()
+            .map(|request| request.split_whitespace().nth(1).unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(paths, ["/", "/missing.png", "/touch.png", "/favicon.ico"]);
+    }
+
+    #[tokio::test]
+    async fn failed_page_falls_back_to_favicon_ico() {
+        let server = TestServer::start(vec![Response::new("500 Internal Server Error"), Response::png()]).await;
+        let page = server.url.trim_end_matches("favicon.ico");
+        let icon = fetch_direct(&test_server::client(), page, &NetworkConfig::default()).await.unwrap();
+        assert_eq!(icon.url.as_deref(), Some(server.url.as_str()));
+        assert_eq!(server.requests().len(), 2);
+    }
+}
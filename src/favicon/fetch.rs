@@ -1,30 +1,397 @@
-use reqwest;
-use base64;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER};
+use serde::{Deserialize, Serialize};
+
+use crate::config::NetworkConfig;
 use crate::errors::{AppError, AppResult};
+use super::abort::until_aborted;
+use super::icon::Icon;
 
-/// 获取favicon并转换为base64编码（异步版本）
-pub async fn fetch_favicon_base64_async(url: &str) -> AppResult<String> {
-    let resp = reqwest::get(url).await?;
-    if !resp.status().is_success() {
-        return Err(AppError::CustomError(format!("HTTP {}", resp.status())));
+/// 获取失败的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// 连接或读取超时
+    Timeout,
+    /// 连接失败或连接被重置
+    Connection,
+    /// 请求过于频繁（HTTP 429）
+    RateLimited,
+    /// 服务端错误（HTTP 5xx）
+    ServerError,
+    /// 图标不存在（HTTP 404/410）
+    NotFound,
+    /// 其他客户端错误（HTTP 4xx）
+    ClientError,
+    /// 返回的内容不是有效图片
+    InvalidImage,
+    /// 服务返回的是默认占位图标
+    Placeholder,
+    /// 其他错误
+    Other,
+}
+
+impl FailureKind {
+    /// 是否为临时性失败，临时性失败会重试，且下次处理时重新获取
+    pub fn is_transient(self) -> bool {
+        matches!(self, FailureKind::Timeout | FailureKind::Connection | FailureKind::RateLimited | FailureKind::ServerError)
+    }
+
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => FailureKind::RateLimited,
+            StatusCode::REQUEST_TIMEOUT => FailureKind::Timeout,
+            StatusCode::NOT_FOUND | StatusCode::GONE => FailureKind::NotFound,
+            status if status.is_server_error() => FailureKind::ServerError,
+            status if status.is_client_error() => FailureKind::ClientError,
+            _ => FailureKind::Other,
+        }
+    }
+
+    fn from_error(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            FailureKind::Timeout
+        } else if error.is_connect() || error.is_request() || error.is_body() {
+            FailureKind::Connection
+        } else if let Some(status) = error.status() {
+            FailureKind::from_status(status)
+        } else {
+            FailureKind::Other
+        }
+    }
+}
+
+/// 从错误中取得失败类型，非获取错误时为 [`FailureKind::Other`]
+pub fn failure_kind(error: &AppError) -> FailureKind {
+    match error {
+        AppError::FetchError { kind, .. } => *kind,
+        AppError::NetworkError(e) => FailureKind::from_error(e),
+        AppError::ImageError(_) => FailureKind::InvalidImage,
+        _ => FailureKind::Other,
+    }
+}
+
+/// 条件请求使用的校验信息，来自上次响应的 `ETag` 和 `Last-Modified`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// 作为 `If-None-Match` 发送
+    pub etag: Option<String>,
+    /// 作为 `If-Modified-Since` 发送
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// 是否有可用的校验信息
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// 条件请求的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Revalidation {
+    /// 服务端返回 304，图标没有变化
+    NotModified,
+    /// 图标已变化或服务端不支持条件请求，返回新的图标
+    Modified(Icon),
+}
+
+fn fetch_error(kind: FailureKind, message: impl Into<String>) -> AppError {
+    AppError::FetchError { kind, message: message.into() }
+}
+
+/// 检查响应状态，失败时返回带失败类型的错误和服务端要求的等待时间
+fn check_status(status: StatusCode, headers: &HeaderMap) -> Result<(), (AppError, Option<Duration>)> {
+    if status.is_success() {
+        return Ok(());
+    }
+    let kind = FailureKind::from_status(status);
+    let retry_after = if kind.is_transient() { retry_after(headers) } else { None };
+    Err((fetch_error(kind, format!("HTTP {}", status)), retry_after))
+}
+
+/// 解析 `Retry-After` 响应头，支持秒数和 HTTP 日期两种形式
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// 校验响应内容，MIME 类型根据数据判断，不信任 `Content-Type`；同时记录地址、`ETag` 和 `Last-Modified`
+fn to_icon(url: &str, headers: &HeaderMap, bytes: Vec<u8>) -> AppResult<Icon> {
+    if bytes.is_empty() {
+        return Err(fetch_error(FailureKind::InvalidImage, "Empty response"));
+    }
+    let header = |name: HeaderName| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    Ok(Icon {
+        url: Some(url.to_string()),
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        ..Icon::from_bytes(bytes)?
+    })
+}
+
+/// 第 `attempt` 次重试前的等待时间
+///
+/// 按指数退避计算，服务端给出 `Retry-After` 时以其为准，均不超过配置的最大等待时间
+fn retry_delay(config: &NetworkConfig, attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let backoff = Duration::from_millis(config.retry_base_delay_ms.saturating_mul(1 << attempt.min(16)));
+    retry_after.unwrap_or(backoff).min(Duration::from_secs(config.retry_max_delay_secs))
+}
+
+async fn fetch_once_async(client: &reqwest::Client, url: &str, validators: &Validators) -> Result<Revalidation, (AppError, Option<Duration>)> {
+    let mut request = client.get(url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let resp = request.send().await.map_err(|e| (AppError::from(e), None))?;
+    if resp.status() == StatusCode::NOT_MODIFIED && !validators.is_empty() {
+        return Ok(Revalidation::NotModified);
+    }
+    check_status(resp.status(), resp.headers())?;
+    let headers = resp.headers().clone();
+    let bytes = resp.bytes().await.map_err(|e| (AppError::from(e), None))?;
+    to_icon(url, &headers, bytes.to_vec()).map(Revalidation::Modified).map_err(|e| (e, None))
+}
+
+fn fetch_once(client: &reqwest::blocking::Client, url: &str) -> Result<Icon, (AppError, Option<Duration>)> {
+    let resp = client.get(url).send().map_err(|e| (AppError::from(e), None))?;
+    check_status(resp.status(), resp.headers())?;
+    let headers = resp.headers().clone();
+    let bytes = resp.bytes().map_err(|e| (AppError::from(e), None))?;
+    to_icon(url, &headers, bytes.to_vec()).map_err(|e| (e, None))
+}
+
+/// 获取图标（异步版本）
+///
+/// 临时性失败按 [`NetworkConfig`] 中的重试次数和退避时间重试。
+/// 本函数不检查中止标志，调用方需要自行中止（提供者链中的请求会与中止标志竞争）
+pub async fn fetch_icon_async(client: &reqwest::Client, url: &str, config: &NetworkConfig) -> AppResult<Icon> {
+    match fetch_with_retry(client, url, &Validators::default(), config, None).await? {
+        Revalidation::Modified(icon) => Ok(icon),
+        Revalidation::NotModified => Err(fetch_error(FailureKind::Other, "HTTP 304 Not Modified")),
     }
-    let mime = resp.headers().get("content-type").and_then(|v| v.to_str().ok()).unwrap_or("image/png").to_string();
-    let bytes = resp.bytes().await?.to_vec();
-    use base64::Engine;
-    let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
-    Ok(format!("data:{};base64,{}", mime, b64))
+}
+
+/// 带校验信息发送条件请求，检查缓存的图标是否变化
+///
+/// 发送 `If-None-Match` 和 `If-Modified-Since`，服务端返回 304 时不下载图标数据；重试规则与 [`fetch_icon_async`] 相同，
+/// 退避等待期间用户中止时立即返回最后一次的错误
+pub async fn revalidate_async(client: &reqwest::Client, url: &str, validators: &Validators, config: &NetworkConfig, abort_flag: &AtomicBool) -> AppResult<Revalidation> {
+    fetch_with_retry(client, url, validators, config, Some(abort_flag)).await
+}
+
+async fn fetch_with_retry(client: &reqwest::Client, url: &str, validators: &Validators, config: &NetworkConfig, abort_flag: Option<&AtomicBool>) -> AppResult<Revalidation> {
+    let mut attempt = 0;
+    loop {
+        match fetch_once_async(client, url, validators).await {
+            Ok(result) => return Ok(result),
+            Err((e, retry_after)) => {
+                if attempt >= config.max_retries || !failure_kind(&e).is_transient() {
+                    return Err(e);
+                }
+                let delay = tokio::time::sleep(retry_delay(config, attempt, retry_after));
+                match abort_flag {
+                    Some(abort_flag) => {
+                        if until_aborted(abort_flag, delay).await.is_none() {
+                            return Err(e);
+                        }
+                    }
+                    None => delay.await,
+                }
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 请求一次并返回响应内容，用于探测服务的默认图标
+///
+/// 服务对不认识的域名可能返回 404 和默认图标，因此除临时性失败外不检查状态码，也不重试
+pub async fn fetch_body_async(client: &reqwest::Client, url: &str) -> AppResult<Vec<u8>> {
+    let resp = client.get(url).send().await?;
+    if let Err((e, _)) = check_status(resp.status(), resp.headers()) {
+        if failure_kind(&e).is_transient() {
+            return Err(e);
+        }
+    }
+    Ok(resp.bytes().await?.to_vec())
+}
+
+/// 获取图标（同步版本）
+///
+/// 重试规则与异步版本相同
+pub fn fetch_icon(client: &reqwest::blocking::Client, url: &str, config: &NetworkConfig) -> AppResult<Icon> {
+    let mut attempt = 0;
+    loop {
+        match fetch_once(client, url) {
+            Ok(icon) => return Ok(icon),
+            Err((e, retry_after)) => {
+                if attempt >= config.max_retries || !failure_kind(&e).is_transient() {
+                    return Err(e);
+                }
+                std::thread::sleep(retry_delay(config, attempt, retry_after));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// 获取favicon并转换为base64编码（异步版本）
+pub async fn fetch_favicon_base64_async(client: &reqwest::Client, url: &str, config: &NetworkConfig) -> AppResult<String> {
+    fetch_icon_async(client, url, config).await.map(|icon| icon.to_data_url())
 }
 
 /// 获取favicon并转换为base64编码（同步版本）
-pub fn fetch_favicon_base64(url: &str) -> AppResult<String> {
-    let resp = reqwest::blocking::get(url)?;
-    if !resp.status().is_success() {
-        return Err(AppError::CustomError(format!("HTTP {}", resp.status())));
-    }
-    let mime = resp.headers().get("content-type").and_then(|v| v.to_str().ok()).unwrap_or("image/png").to_string();
-    let bytes = resp.bytes()?.to_vec();
-    use base64::Engine;
-    let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
-    Ok(format!("data:{};base64,{}", mime, b64))
-}
\ No newline at end of file
+pub fn fetch_favicon_base64(client: &reqwest::blocking::Client, url: &str, config: &NetworkConfig) -> AppResult<String> {
+    fetch_icon(client, url, config).map(|icon| icon.to_data_url())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::Ordering;
+    use std::time::Instant;
+    use super::super::test_server::{self, Response, TestServer};
+
+    /// 退避时间很短的配置，只有 `Retry-After` 会明显延长测试时间
+    fn config() -> NetworkConfig {
+        NetworkConfig { max_retries: 2, retry_base_delay_ms: 10, retry_max_delay_secs: 5, ..NetworkConfig::default() }
+    }
+
+    async fn fetch(server: &TestServer, config: &NetworkConfig) -> AppResult<Icon> {
+        fetch_icon_async(&test_server::client(), &server.url, config).await
+    }
+
+    #[tokio::test]
+    async fn rate_limit_waits_for_retry_after_seconds() {
+        let server = TestServer::start(vec![
+            Response::new("429 Too Many Requests").header("Retry-After", "1"),
+            Response::png(),
+        ]).await;
+        let started = Instant::now();
+        let icon = fetch(&server, &config()).await.unwrap();
+        assert_eq!(icon.mime, "image/png");
+        assert_eq!(server.requests().len(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_waits_for_retry_after_date_capped_by_max_delay() {
+        // HTTP 日期形式要求等待一小时，实际等待不超过配置的 1 秒
+        let date = (Utc::now() + chrono::Duration::hours(1)).to_rfc2822();
+        let server = TestServer::start(vec![
+            Response::new("429 Too Many Requests").header("Retry-After", date),
+            Response::png(),
+        ]).await;
+        let config = NetworkConfig { retry_max_delay_secs: 1, ..config() };
+        let started = Instant::now();
+        fetch(&server, &config).await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(900), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(3), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn retry_after_parses_seconds_and_http_date() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        headers.insert(RETRY_AFTER, date.parse().unwrap());
+        let delay = retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60), "{:?}", delay);
+
+        // 已经过去的日期不需要等待
+        headers.insert(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn retry_delay_is_capped_by_max_delay() {
+        let config = NetworkConfig { retry_base_delay_ms: 500, retry_max_delay_secs: 3, ..NetworkConfig::default() };
+        assert_eq!(retry_delay(&config, 0, None), Duration::from_millis(500));
+        assert_eq!(retry_delay(&config, 2, None), Duration::from_secs(2));
+        assert_eq!(retry_delay(&config, 10, None), Duration::from_secs(3));
+        assert_eq!(retry_delay(&config, 0, Some(Duration::from_secs(120))), Duration::from_secs(3));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_until_success() {
+        let server = TestServer::start(vec![
+            Response::new("503 Service Unavailable"),
+            Response::new("500 Internal Server Error"),
+            Response::png(),
+        ]).await;
+        let icon = fetch(&server, &config()).await.unwrap();
+        assert_eq!(icon.url.as_deref(), Some(server.url.as_str()));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn server_errors_give_up_after_max_retries() {
+        let server = TestServer::start(vec![
+            Response::new("502 Bad Gateway"),
+            Response::new("502 Bad Gateway"),
+            Response::new("502 Bad Gateway"),
+            Response::png(),
+        ]).await;
+        let error = fetch(&server, &config()).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::ServerError);
+        assert!(failure_kind(&error).is_transient());
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn not_found_is_permanent_and_not_retried() {
+        let server = TestServer::start(vec![Response::new("404 Not Found"), Response::png()]).await;
+        let error = fetch(&server, &config()).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::NotFound);
+        assert!(!failure_kind(&error).is_transient());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn html_body_is_permanent_and_not_retried() {
+        let server = TestServer::start(vec![
+            Response::new("200 OK").header("Content-Type", "image/png").body("<!DOCTYPE html><html><body>Not here</body></html>"),
+            Response::png(),
+        ]).await;
+        let error = fetch(&server, &config()).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::InvalidImage);
+        assert!(!failure_kind(&error).is_transient());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn abort_interrupts_backoff() {
+        let server = TestServer::start(vec![
+            Response::new("429 Too Many Requests").header("Retry-After", "30"),
+            Response::png(),
+        ]).await;
+        let config = NetworkConfig { retry_max_delay_secs: 60, ..config() };
+        let abort_flag = Arc::new(AtomicBool::new(false));
+        let setter = abort_flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            setter.store(true, Ordering::Relaxed);
+        });
+        let started = Instant::now();
+        let error = revalidate_async(&test_server::client(), &server.url, &Validators::default(), &config, &abort_flag).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::RateLimited);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
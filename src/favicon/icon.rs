@@ -0,0 +1,44 @@
+//! 图标数据
+
+use crate::errors::AppResult;
+use crate::utils::{data_url, image};
+
+/// 获取到的图标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    /// MIME 类型
+    pub mime: String,
+    /// 图片数据
+    pub data: Vec<u8>,
+    /// 图标的下载地址，本地或生成的图标为 None
+    pub url: Option<String>,
+    /// 响应中的 `ETag`，用于之后校验图标是否变化
+    pub etag: Option<String>,
+    /// 响应中的 `Last-Modified`
+    pub last_modified: Option<String>,
+}
+
+impl Icon {
+    /// 创建图标
+    pub fn new(mime: impl Into<String>, data: Vec<u8>) -> Self {
+        Self { mime: mime.into(), data, url: None, etag: None, last_modified: None }
+    }
+
+    /// 从图片数据创建图标，校验数据并根据文件头确定 MIME 类型
+    pub fn from_bytes(data: Vec<u8>) -> AppResult<Self> {
+        let mime = image::validate(&data)?;
+        Ok(Self::new(mime, data))
+    }
+
+    /// 从 base64 data URL 解析图标，数据无效时返回 None
+    ///
+    /// MIME 类型以数据本身为准，不使用 data URL 中声明的类型
+    pub fn from_data_url(url: &str) -> Option<Self> {
+        data_url::decode(url).and_then(|(_, data)| Self::from_bytes(data).ok())
+    }
+
+    /// 编码为 base64 data URL
+    pub fn to_data_url(&self) -> String {
+        data_url::encode(&self.mime, &self.data)
+    }
+}
@@ -2,10 +2,28 @@
 //!
 //! 提供获取和处理网站favicon的功能
 
+pub mod abort;
+pub mod avatar;
 pub mod cache;
+pub mod client;
+pub mod discover;
 pub mod fetch;
+pub mod icon;
+pub mod normalize;
+pub mod placeholder;
 pub mod process;
+pub mod provider;
+pub mod store;
+pub mod svg;
+#[cfg(test)]
+mod test_server;
 
-pub use cache::{FaviconCache, get_cache_path};
-pub use fetch::{fetch_favicon_base64, fetch_favicon_base64_async};
-pub use process::process_bookmarks;
\ No newline at end of file
+pub use cache::{CacheEntry, FaviconCache, get_cache_db_path, get_cache_path};
+pub use client::{build_blocking_client, build_client};
+pub use fetch::{fetch_favicon_base64, fetch_favicon_base64_async, fetch_icon, fetch_icon_async, revalidate_async, FailureKind, Revalidation, Validators};
+pub use icon::Icon;
+pub use normalize::{normalize, normalize_bookmarks, NormalizeStats};
+pub use placeholder::{content_hash, is_placeholder, matches_placeholder, perceptual_hash};
+pub use process::{add_favicons, add_favicons_with_providers, process_bookmarks, ProcessStats};
+pub use provider::{BookmarkContext, FaviconProvider, providers_from_config};
+pub use store::{open_store, CacheStore, JsonStore, Migration, SqliteStore};
\ No newline at end of file
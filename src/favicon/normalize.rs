@@ -0,0 +1,280 @@
+//! 图标规范化
+//!
+//! 把图标统一转换成小尺寸 PNG，减小输出文件：ICO 选取最合适的一帧，
+//! 动画 GIF 取第一帧，SVG 栅格化，大于目标尺寸的图片按比例缩小，再以最高压缩率编码
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+
+use crate::bookmarks::Bookmark;
+use crate::errors::AppResult;
+use crate::utils::format::format_size;
+use super::icon::Icon;
+
+/// 规范化统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NormalizeStats {
+    /// 图标被转换的书签数
+    pub count: usize,
+    /// 转换前的图标大小（data URL 字节数）
+    pub original_bytes: usize,
+    /// 转换后的图标大小（data URL 字节数）
+    pub normalized_bytes: usize,
+}
+
+impl NormalizeStats {
+    /// 节省的字节数
+    pub fn saved_bytes(&self) -> usize {
+        self.original_bytes.saturating_sub(self.normalized_bytes)
+    }
+}
+
+/// 把图标转换为不超过 `size` 像素的 PNG
+///
+/// SVG 按目标尺寸栅格化；原图已是不超过目标尺寸的 PNG 且转换后没有变小时保持不变
+pub fn normalize(icon: &Icon, size: u32) -> AppResult<Icon> {
+    let image = match icon.mime.as_str() {
+        "image/svg+xml" => return super::svg::rasterize(&icon.data, size),
+        "image/x-icon" => decode_ico(&icon.data, size)?,
+        // GIF 只解码第一帧
+        _ => image::load_from_memory(&icon.data)?,
+    };
+    let image = if image.width() > size || image.height() > size {
+        image.resize(size, size, FilterType::Lanczos3)
+    } else {
+        image
+    };
+    let data = encode_png(image)?;
+    let unchanged = icon.mime == "image/png" && data.len() >= icon.data.len() && fits(&icon.data, size);
+    if unchanged {
+        return Ok(icon.clone());
+    }
+    Ok(Icon::new("image/png", data))
+}
+
+/// 判断 PNG 是否不超过目标尺寸
+fn fits(data: &[u8], size: u32) -> bool {
+    image::ImageReader::with_format(Cursor::new(data), ImageFormat::Png)
+        .into_dimensions()
+        .is_ok_and(|(width, height)| width <= size && height <= size)
+}
+
+/// 编码为 PNG，完全不透明的图片去掉 alpha 通道
+pub(super) fn encode_png(image: DynamicImage) -> AppResult<Vec<u8>> {
+    let rgba = image.to_rgba8();
+    let image = if rgba.pixels().all(|pixel| pixel.0[3] == 255) {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).to_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(rgba)
+    };
+    let mut data = Vec::new();
+    image.write_with_encoder(PngEncoder::new_with_quality(&mut data, CompressionType::Best, PngFilter::Adaptive))?;
+    Ok(data)
+}
+
+/// ICO 目录项
+struct IcoEntry<'a> {
+    width: u32,
+    height: u32,
+    bit_count: u16,
+    header: &'a [u8],
+    data: &'a [u8],
+}
+
+/// 解码 ICO 中最合适的一帧：不小于目标尺寸的最小帧，都小于目标尺寸时取最大帧，同尺寸取色深最高的
+fn decode_ico(data: &[u8], size: u32) -> AppResult<DynamicImage> {
+    let entries = ico_entries(data);
+    let best = entries.iter()
+        .filter(|entry| entry.width >= size && entry.height >= size)
+        .min_by_key(|entry| (entry.width * entry.height, u16::MAX - entry.bit_count))
+        .or_else(|| entries.iter().max_by_key(|entry| (entry.width * entry.height, entry.bit_count)));
+    let Some(best) = best else {
+        return Ok(image::load_from_memory_with_format(data, ImageFormat::Ico)?);
+    };
+    // 只保留选中的一帧重新组成 ICO，交给 image 解码 PNG 或 BMP 帧
+    let mut single = vec![0, 0, 1, 0, 1, 0];
+    single.extend_from_slice(&best.header[..12]);
+    single.extend_from_slice(&22u32.to_le_bytes());
+    single.extend_from_slice(best.data);
+    Ok(image::load_from_memory_with_format(&single, ImageFormat::Ico)?)
+}
+
+/// 解析 ICO 目录，跳过越界的目录项
+fn ico_entries(data: &[u8]) -> Vec<IcoEntry<'_>> {
+    let count = data.get(4..6).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize).unwrap_or(0);
+    (0..count)
+        .filter_map(|i| {
+            let header = data.get(6 + i * 16..6 + (i + 1) * 16)?;
+            let bit_count = u16::from_le_bytes([header[6], header[7]]);
+            let length = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+            let offset = u32::from_le_bytes([header[12], header[13], header[14], header[15]]) as usize;
+            let data = data.get(offset..offset.checked_add(length)?)?;
+            // 宽高为 0 表示 256
+            let dimension = |value: u8| if value == 0 { 256 } else { value as u32 };
+            Some(IcoEntry { width: dimension(header[0]), height: dimension(header[1]), bit_count, header, data })
+        })
+        .collect()
+}
+
+/// 规范化书签中的图标，逐个记录节省的大小
+///
+/// 同样的图标只转换一次；无法转换的图标保持不变
+pub fn normalize_bookmarks(bookmarks: &mut [&mut Bookmark], size: u32, log: &Arc<Mutex<String>>) -> NormalizeStats {
+    let mut stats = NormalizeStats::default();
+    let mut converted: HashMap<String, Option<String>> = HashMap::new();
+    for bookmark in bookmarks.iter_mut() {
+        let Some(icon) = bookmark.icon.as_ref() else {
+            continue;
+        };
+        let normalized = converted.entry(icon.clone())
+            .or_insert_with(|| {
+                let original = Icon::from_data_url(icon)?;
+                let normalized = normalize(&original, size).ok()?;
+                (normalized != original).then(|| normalized.to_data_url())
+            })
+            .clone();
+        let Some(normalized) = normalized else {
+            continue;
+        };
+
+        let before = icon.len();
+        let after = normalized.len();
+        stats.count += 1;
+        stats.original_bytes += before;
+        stats.normalized_bytes += after;
+        if let Ok(mut log_lock) = log.lock() {
+            let mut args = HashMap::new();
+            args.insert("title".to_string(), bookmark.title.clone());
+            args.insert("before".to_string(), format_size(before));
+            args.insert("after".to_string(), format_size(after));
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("icon_normalized", Some(args))));
+        }
+        bookmark.icon = Some(normalized);
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::codecs::ico::{IcoEncoder, IcoFrame};
+    use image::{ExtendedColorType, Frame, Rgba, RgbaImage};
+
+    /// 带渐变的图片，避免被压缩得过小
+    fn gradient(size: u32, color: [u8; 3]) -> RgbaImage {
+        RgbaImage::from_fn(size, size, |x, y| Rgba([color[0], color[1].wrapping_add((x * 7) as u8), color[2].wrapping_add((y * 5) as u8), 255]))
+    }
+
+    fn png(image: RgbaImage) -> Icon {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+        Icon::from_bytes(data).unwrap()
+    }
+
+    /// 16、32、64 像素三帧的 ICO，颜色分别以红、绿、蓝为主
+    fn ico() -> Vec<u8> {
+        let frames: Vec<IcoFrame> = [(16, [255, 0, 0]), (32, [0, 255, 0]), (64, [0, 0, 255])].into_iter()
+            .map(|(size, color)| {
+                let image = RgbaImage::from_pixel(size, size, Rgba([color[0], color[1], color[2], 255]));
+                IcoFrame::as_png(image.as_raw(), size, size, ExtendedColorType::Rgba8).unwrap()
+            })
+            .collect();
+        let mut data = Vec::new();
+        IcoEncoder::new(&mut data).encode_images(&frames).unwrap();
+        data
+    }
+
+    #[test]
+    fn ico_frame_closest_to_target_is_chosen() {
+        let data = ico();
+        assert_eq!(ico_entries(&data).len(), 3);
+        let pixel = |size| {
+            let image = decode_ico(&data, size).unwrap();
+            (image.width(), image.to_rgba8().get_pixel(0, 0).0)
+        };
+        // 不小于目标尺寸的最小帧
+        assert_eq!(pixel(32), (32, [0, 255, 0, 255]));
+        assert_eq!(pixel(20), (32, [0, 255, 0, 255]));
+        assert_eq!(pixel(48), (64, [0, 0, 255, 255]));
+        // 都小于目标尺寸时取最大帧
+        assert_eq!(pixel(128), (64, [0, 0, 255, 255]));
+
+        let icon = Icon::from_bytes(data).unwrap();
+        assert_eq!(icon.mime, "image/x-icon");
+        let normalized = normalize(&icon, 48).unwrap();
+        assert_eq!(normalized.mime, "image/png");
+        assert_eq!(image::load_from_memory(&normalized.data).unwrap().width(), 48);
+    }
+
+    #[test]
+    fn gif_uses_first_frame() {
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                encoder.encode_frame(Frame::new(RgbaImage::from_pixel(16, 16, Rgba(color)))).unwrap();
+            }
+        }
+        let normalized = normalize(&Icon::from_bytes(data).unwrap(), 32).unwrap();
+        assert_eq!(normalized.mime, "image/png");
+        let image = image::load_from_memory(&normalized.data).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 16));
+        assert_eq!(image.to_rgba8().get_pixel(8, 8).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn large_images_are_downscaled_keeping_aspect_ratio() {
+        let wide = DynamicImage::ImageRgba8(gradient(128, [10, 20, 30])).crop_imm(0, 0, 128, 64).to_rgba8();
+        let normalized = normalize(&png(wide), 32).unwrap();
+        let image = image::load_from_memory(&normalized.data).unwrap();
+        assert_eq!((image.width(), image.height()), (32, 16));
+    }
+
+    #[test]
+    fn small_png_is_unchanged_unless_smaller() {
+        let icon = normalize(&png(gradient(16, [10, 20, 30])), 32).unwrap();
+        // 已经是最高压缩率的 PNG，再次转换不会变小
+        assert_eq!(normalize(&icon, 32).unwrap(), icon);
+
+        // 带有多余数据的 PNG 重新编码后变小
+        let mut padded = icon.data.clone();
+        let end = padded.len() - 12;
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&1024u32.to_be_bytes());
+        chunk.extend_from_slice(b"tEXt");
+        chunk.extend(std::iter::repeat_n(b'x', 1024));
+        chunk.extend_from_slice(&[0; 4]);
+        padded.splice(end..end, chunk);
+        let padded = Icon::new("image/png", padded);
+        let normalized = normalize(&padded, 32).unwrap();
+        assert!(normalized.data.len() < padded.data.len());
+    }
+
+    #[test]
+    fn bookmarks_are_normalized_once_per_icon() {
+        let large = png(gradient(64, [200, 100, 50])).to_data_url();
+        let small = normalize(&png(gradient(16, [10, 20, 30])), 32).unwrap().to_data_url();
+        let mut bookmarks: Vec<Bookmark> = [Some(large.clone()), Some(large.clone()), Some(small.clone()), Some("data:image/png;base64,AAAA".to_string()), None]
+            .into_iter()
+            .enumerate()
+            .map(|(i, icon)| Bookmark { icon, ..Bookmark::new(format!("Bookmark {}", i), "https://example.com/") })
+            .collect();
+        let mut refs: Vec<&mut Bookmark> = bookmarks.iter_mut().collect();
+        let log = Arc::new(Mutex::new(String::new()));
+        let stats = normalize_bookmarks(&mut refs, 32, &log);
+
+        let normalized = bookmarks[0].icon.clone().unwrap();
+        assert_eq!(bookmarks[1].icon.as_ref(), Some(&normalized));
+        assert_eq!(bookmarks[2].icon.as_ref(), Some(&small));
+        assert_eq!(bookmarks[3].icon.as_deref(), Some("data:image/png;base64,AAAA"));
+        assert_eq!(stats, NormalizeStats { count: 2, original_bytes: large.len() * 2, normalized_bytes: normalized.len() * 2 });
+        assert_eq!(stats.saved_bytes(), (large.len() - normalized.len()) * 2);
+        assert_eq!(log.lock().unwrap().lines().count(), 2);
+    }
+}
@@ -0,0 +1,119 @@
+//! 占位图标识别
+//!
+//! 识别书签中已有的无效图标：非 data URL、空数据、base64 无法解码，或所有像素颜色相同的空白图片；
+//! 以及服务不认识域名时返回的默认图标，按内容哈希（`md5:`）或感知哈希（`dhash:`）比较
+
+use image::{GenericImageView, imageops::FilterType};
+
+use crate::utils::data_url;
+
+/// 判断图标是否为占位图标
+pub fn is_placeholder(icon: &str) -> bool {
+    let Some((mime, bytes)) = data_url::decode(icon) else {
+        // 未经 base64 编码的 data URL（如 `data:image/svg+xml,<svg ...>`）无需解码，有内容即视为有效
+        return !is_plain_data_url(icon);
+    };
+    if bytes.is_empty() {
+        return true;
+    }
+    // SVG 无法用 image 解码，按有效图标处理
+    if mime == "image/svg+xml" {
+        return false;
+    }
+    let Ok(image) = image::load_from_memory(&bytes) else {
+        return true;
+    };
+    let mut pixels = image.pixels().map(|(_, _, pixel)| pixel);
+    match pixels.next() {
+        Some(first) => pixels.all(|pixel| pixel == first),
+        None => true,
+    }
+}
+
+/// 是否为带有内容、未经 base64 编码的 data URL
+fn is_plain_data_url(icon: &str) -> bool {
+    icon.trim().strip_prefix("data:")
+        .and_then(|rest| rest.split_once(','))
+        .is_some_and(|(meta, data)| !meta.ends_with(";base64") && !data.trim().is_empty())
+}
+
+/// 感知哈希允许的最大差异位数
+const DHASH_TOLERANCE: u32 = 4;
+
+/// 图片数据的内容哈希，格式为 `md5:<十六进制>`
+pub fn content_hash(data: &[u8]) -> String {
+    format!("md5:{:x}", md5::compute(data))
+}
+
+/// 图片的感知哈希（差值哈希），格式为 `dhash:<十六进制>`，无法解码时返回 None
+///
+/// 透明部分按白色背景计算，同一图标缩放或重新编码后哈希基本不变
+pub fn perceptual_hash(data: &[u8]) -> Option<String> {
+    let image = image::load_from_memory(data).ok()?.to_rgba8();
+    let mut flattened = image::GrayImage::new(image.width(), image.height());
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        let alpha = a as u32;
+        flattened.put_pixel(x, y, image::Luma([((luma * alpha + 255 * (255 - alpha)) / 255) as u8]));
+    }
+    let small = image::imageops::resize(&flattened, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Some(format!("dhash:{:016x}", hash))
+}
+
+/// 判断图片是否与已知的占位图标哈希相同
+///
+/// `md5:` 哈希要求完全一致，`dhash:` 哈希允许少量差异；不带前缀的 32 位十六进制按 `md5:` 处理
+pub fn matches_placeholder(data: &[u8], hashes: &[String]) -> bool {
+    if hashes.is_empty() {
+        return false;
+    }
+    let content = content_hash(data);
+    let mut perceptual = None;
+    hashes.iter().any(|hash| {
+        let hash = hash.trim().to_ascii_lowercase();
+        if let Some(expected) = hash.strip_prefix("dhash:") {
+            let Ok(expected) = u64::from_str_radix(expected, 16) else {
+                return false;
+            };
+            let actual = perceptual.get_or_insert_with(|| perceptual_hash(data)
+                .and_then(|hash| u64::from_str_radix(&hash["dhash:".len()..], 16).ok()));
+            actual.is_some_and(|actual| (actual ^ expected).count_ones() <= DHASH_TOLERANCE)
+        } else {
+            let expected = hash.strip_prefix("md5:").unwrap_or(&hash);
+            content["md5:".len()..] == *expected
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_svg_data_url_is_not_placeholder() {
+        assert!(!is_placeholder("data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg'%3E%3C/svg%3E"));
+        assert!(!is_placeholder("data:image/svg+xml;charset=utf-8,<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+    }
+
+    #[test]
+    fn invalid_icons_are_placeholders() {
+        assert!(is_placeholder("https://example.com/favicon.ico"));
+        assert!(is_placeholder("data:image/png;base64,not base64!"));
+        assert!(is_placeholder("data:image/png;base64,"));
+        assert!(is_placeholder("data:image/svg+xml,"));
+        let blank = image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 255, 255, 255]));
+        let mut png = Vec::new();
+        blank.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+        assert!(is_placeholder(&data_url::encode("image/png", &png)));
+    }
+}
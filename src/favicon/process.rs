@@ -1,203 +1,666 @@
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::collections::HashMap;
-use std::fs;
-use regex::Regex;
+use std::sync::atomic::AtomicBool;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use chrono::Local;
-use url::Url;
+use futures::stream::{self, StreamExt};
 
 use crate::errors::{AppError, AppResult};
 use crate::config::AppConfig;
-use super::cache::FaviconCache;
+use crate::bookmarks::{BookmarkFormat, BookmarkTree};
+use super::abort::until_aborted;
+use super::cache::CacheEntry;
+use super::fetch::{failure_kind, revalidate_async, Revalidation};
+use super::icon::Icon;
+use super::provider::{BookmarkContext, FaviconProvider, providers_from_config};
+use super::store::{open_store, CacheStore};
 
-/// 从URL中提取域名
-fn extract_domain(url: &str) -> Option<String> {
-    if let Ok(parsed_url) = Url::parse(url) {
-        if let Some(host) = parsed_url.host_str() {
-            return Some(host.to_string());
+/// 写入缓存条目，失败时记录日志
+fn put_cache(store: &mut dyn CacheStore, domain: &str, entry: &CacheEntry, log: &Arc<Mutex<String>>) {
+    if let Err(e) = store.put(domain, entry) {
+        if let Ok(mut log_lock) = log.lock() {
+            log_lock.push_str(&format!("Failed to write cache file: {}\n", e));
         }
     }
-    None
 }
 
 /// 保存缓存到磁盘
-fn save_cache(cache: &HashMap<String, Option<String>>, cache_path: &str, log: &Arc<Mutex<String>>) {
-    let cache = FaviconCache(cache.clone());
-    if let Err(e) = fs::write(cache_path, serde_json::to_string_pretty(&cache).unwrap_or_default()) {
+fn flush_cache(store: &mut dyn CacheStore, log: &Arc<Mutex<String>>) {
+    if let Err(e) = store.flush() {
         if let Ok(mut log_lock) = log.lock() {
             log_lock.push_str(&format!("Failed to write cache file: {}\n", e));
         }
     }
 }
 
-/// 处理书签文件，为其中的链接添加favicon
-pub async fn process_bookmarks(input: &str, output: &str, log: Arc<Mutex<String>>, abort_flag: Arc<AtomicBool>, progress: Arc<Mutex<(usize, usize)>>) -> AppResult<()> {
-    // favicon 缓存文件路径
-    let cache_path = super::cache::get_cache_path();
-    // 加载磁盘缓存
-    let mut favicon_cache: HashMap<String, Option<String>> = if let Ok(data) = fs::read_to_string(&cache_path) {
-        serde_json::from_str::<FaviconCache>(&data).map(|c| c.0).unwrap_or_default()
-    } else {
-        HashMap::new()
-    };
+/// 处理统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessStats {
+    /// 获取到favicon的书签数
+    pub success: usize,
+    /// 获取失败的书签数，包括使用生成图标的书签
+    pub failed: usize,
+    /// 获取失败后使用生成的字母图标的书签数
+    pub generated: usize,
+    /// 按已有图标处理策略保留原图标的书签数
+    pub kept: usize,
+    /// 书签总数
+    pub total: usize,
+    /// 图标规范化节省的字节数
+    pub bytes_saved: usize,
+    /// 是否被用户中止
+    pub aborted: bool,
+}
 
-    // 1. 读取 HTML 文件
-    if let Ok(mut log_lock) = log.lock() {
-        log_lock.push_str("\n----------------------------------------\n");
-        log_lock.push_str(&format!("[{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), crate::i18n::get_message("starting_to_process", None)));
+/// SVG 图标按 `svg_size` 栅格化为 PNG，其他格式原样返回
+fn rasterize_svg(icon: Icon, svg_size: u32) -> AppResult<Icon> {
+    match icon.mime.as_str() {
+        "image/svg+xml" => super::svg::rasterize(&icon.data, svg_size).map(|png| Icon { mime: png.mime, data: png.data, ..icon }),
+        _ => Ok(icon),
     }
-    let html_str = match fs::read_to_string(input) {
-        Ok(s) => s,
-        Err(e) => {
-            if let Ok(mut log_lock) = log.lock() {
-                log_lock.push_str(&format!("Failed to read file: {}\n", e));
+}
+
+/// 按提供者顺序依次获取favicon，返回图标和获取成功的提供者名称
+///
+/// SVG 图标按 `svg_size` 栅格化为 PNG 后再写入缓存，无法渲染时换下一个提供者。
+/// 所有提供者都失败时，只要有一个是临时性失败就返回该错误，以便下次处理时重试。
+/// 已中止时立即返回 None
+async fn fetch_from_chain(providers: &[Box<dyn FaviconProvider>], context: &BookmarkContext, svg_size: u32, abort_flag: &AtomicBool) -> Option<AppResult<(Icon, String)>> {
+    let mut error: Option<AppError> = None;
+    for provider in providers {
+        // 已中止时不再发起新的请求，进行中的请求随之丢弃
+        let result = until_aborted(abort_flag, provider.fetch(context)).await?;
+        match result.and_then(|icon| rasterize_svg(icon, svg_size)) {
+            Ok(icon) => return Some(Ok((icon, provider.name().to_string()))),
+            Err(e) => {
+                let keep_previous = error.as_ref().is_some_and(|previous| failure_kind(previous).is_transient());
+                if !keep_previous {
+                    error = Some(e);
+                }
             }
-            return Err(AppError::FileError(e));
         }
-    };
-    if let Ok(mut log_lock) = log.lock() {
-        log_lock.push_str(&format!("Successfully read bookmarks file, {} bytes\n", html_str.len()));
     }
+    Some(Err(error.unwrap_or_else(|| AppError::CustomError("No favicon service enabled".to_string()))))
+}
 
-    // 2. 查找所有书签链接
-    let re_a = Regex::new(r#"<A\b[^>]*HREF\s*=\s*['"](.*?)['"][^>]*>"#).unwrap();
-    let matches: Vec<_> = re_a.find_iter(&html_str).collect();
-    let total = matches.len();
-    if let Ok(mut progress_lock) = progress.lock() {
-        *progress_lock = (0, total);
+/// 为书签树中的所有书签添加favicon
+///
+/// 与文件格式无关，各种书签来源解析成 [`BookmarkTree`] 后都经由此函数处理。
+/// 先按域名去重，缓存中没有的域名按配置的并发数同时获取，最后把结果写回各个书签
+pub async fn add_favicons(tree: &mut BookmarkTree, config: &AppConfig, log: Arc<Mutex<String>>, abort_flag: Arc<AtomicBool>, progress: Arc<Mutex<(usize, usize)>>) -> AppResult<ProcessStats> {
+    let providers = providers_from_config(config)?;
+    add_favicons_with_providers(tree, config, &providers, log, abort_flag, progress).await
+}
+
+/// 使用指定的提供者为书签树添加favicon
+///
+/// 提供者按顺序依次尝试，配置中的服务列表被忽略，已有图标处理策略、并发数等其他设置仍按配置
+pub async fn add_favicons_with_providers(tree: &mut BookmarkTree, config: &AppConfig, providers: &[Box<dyn FaviconProvider>], log: Arc<Mutex<String>>, abort_flag: Arc<AtomicBool>, progress: Arc<Mutex<(usize, usize)>>) -> AppResult<ProcessStats> {
+    // 打开缓存，按域名逐个读取
+    let (mut store, migration) = open_store(&config.cache)?;
+    if let Ok(mut log_lock) = log.lock() {
+        if let Some(recovery) = store.take_recovery() {
+            log_lock.push_str(&format!("{}\n", recovery.message()));
+        }
+        if let Some(migration) = migration {
+            log_lock.push_str(&format!("{}\n", migration.message()));
+        }
     }
+    add_favicons_to_store(tree, config, providers, store.as_mut(), log, abort_flag, progress).await
+}
+
+async fn add_favicons_to_store(tree: &mut BookmarkTree, config: &AppConfig, providers: &[Box<dyn FaviconProvider>], store: &mut dyn CacheStore, log: Arc<Mutex<String>>, abort_flag: Arc<AtomicBool>, progress: Arc<Mutex<(usize, usize)>>) -> AppResult<ProcessStats> {
+    let mut bookmarks = tree.bookmarks_mut();
+    let total = bookmarks.len();
     if let Ok(mut log_lock) = log.lock() {
         log_lock.push_str(&format!("{} {} {}\n", crate::i18n::get_message("found", None), total, crate::i18n::get_message("bookmarks", None)));
     }
 
-    // 3. 处理每个书签链接
-    let mut processed_html = html_str.clone();
-    let mut offset = 0;
-    let config = AppConfig::load();
-    let mut processed = 0;
-    let mut success_count = 0;
-    let mut failed_count = 0;
-    let mut last_save = 0;
+    // 1. 按域名分组，同一域名只获取一次
+    let mut kept_count = 0;
+    let mut no_domain = 0;
+    let mut domains: Vec<BookmarkContext> = Vec::new();
+    let mut domain_bookmarks: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, bookmark) in bookmarks.iter().enumerate() {
+        // 按策略保留已有图标
+        if !config.icon_policy.should_fetch(bookmark.icon.as_deref()) {
+            kept_count += 1;
+            continue;
+        }
+        match bookmark.domain() {
+            Some(domain) => {
+                let indices = domain_bookmarks.entry(domain.clone()).or_default();
+                if indices.is_empty() {
+                    domains.push(BookmarkContext { domain, url: bookmark.url.clone(), title: bookmark.title.clone() });
+                }
+                indices.push(index);
+            }
+            None => no_domain += 1,
+        }
+    }
 
-    for mat in matches {
-        // 检查是否需要中止
-        if abort_flag.load(Ordering::Relaxed) {
-            if let Ok(mut log_lock) = log.lock() {
-                log_lock.push_str(&format!("{}\n", crate::i18n::get_message("processing_aborted_by_user", None)));
-            }
-            // 中止时保存缓存
-            save_cache(&favicon_cache, &cache_path, &log);
-            return Ok(());
-        }
-
-        // 提取URL
-        let url = &mat.as_str();
-        let href_match = Regex::new(r#"HREF\s*=\s*['"](.*?)['"]"#).unwrap()
-            .find(url)
-            .ok_or_else(|| AppError::CustomError("Failed to extract HREF".to_string()))?;
-        let url_str = &url[href_match.start() + 6..href_match.end() - 1];
-
-        // 提取域名
-        if let Some(domain) = extract_domain(url_str) {
-            // 获取favicon
-            let favicon = if let Ok(mut log_lock) = log.lock() {
-                log_lock.push_str(&format!("[{:>3}/{}] {} {}... ", processed, total, crate::i18n::get_message("fetching", None), domain));
-
-                let result = if let Some(cached) = favicon_cache.get(&domain) {
-                    if let Some(favicon) = cached {
-                        log_lock.push_str(&format!("{}\n", crate::i18n::get_message("success", None)));
-                        success_count += 1;
-                        Some(favicon.clone())
-                    } else {
-                        log_lock.push_str(&format!("{}: {}\n", crate::i18n::get_message("failed", None), crate::i18n::get_message("last_request_failed", None)));
-                        failed_count += 1;
-                        None
-                    }
-                } else {
-                    // 从网络获取favicon
-                    let favicon_url = config.get_favicon_url(&domain);
-                    match super::fetch::fetch_favicon_base64_async(&favicon_url).await {
-                        Ok(favicon) => {
-                            favicon_cache.insert(domain.clone(), Some(favicon.clone()));
-                            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("success", None)));
-                            success_count += 1;
-                            Some(favicon)
-                        }
-                        Err(e) => {
-                            log_lock.push_str(&format!("{}: {}\n", crate::i18n::get_message("failed", None), e));
-                            favicon_cache.insert(domain.clone(), None);
-                            failed_count += 1;
-                            None
-                        }
+    let mut processed = kept_count + no_domain;
+    if let Ok(mut progress_lock) = progress.lock() {
+        *progress_lock = (processed, total);
+    }
+
+    // 2. 先使用缓存中未过期的条目，缓存中没有、已过期和上次临时性失败的域名再从网络获取；
+    //    已过期或要求刷新的图标有校验信息时，先发送条件请求
+    let mut favicons: HashMap<String, Option<String>> = HashMap::new();
+    let mut pending = Vec::new();
+    let mut revalidating = Vec::new();
+    // 需要重新获取的域名原有的缓存条目，用于条件请求和获取失败时继续使用过期图标
+    let mut expired: HashMap<String, CacheEntry> = HashMap::new();
+    // 使用生成的字母图标的域名
+    let mut generated_domains = HashSet::new();
+    let now = chrono::Utc::now().timestamp();
+    for context in domains {
+        let domain = context.domain.clone();
+        let cached = store.get(&domain)?;
+        let revalidation = cached.as_ref().and_then(|cached| cached.revalidation());
+        let fresh = cached.as_ref().filter(|cached| cached.is_fresh(&config.cache, now))
+            .filter(|_| !(config.refresh_cached_icons && revalidation.is_some()));
+        match fresh {
+            Some(cached) => {
+                let last_failed = format!("{}: {}", crate::i18n::get_message("failed", None), crate::i18n::get_message("last_request_failed", None));
+                // 上次永久性失败后生成的图标，关闭生成字母图标后不再使用
+                let icon = cached.icon().filter(|_| !cached.is_generated() || config.generate_fallback_icons);
+                let message = match icon {
+                    Some(_) if cached.is_generated() => {
+                        generated_domains.insert(domain.clone());
+                        format!("{} ({})", last_failed, crate::i18n::get_message("generated_icon", None))
                     }
+                    Some(_) => crate::i18n::get_message("success", None),
+                    None => last_failed,
                 };
-                result
-            } else {
-                None
-            };
-
-            // 添加favicon到书签链接
-            if let Some(favicon) = favicon {
-                let icon_tag = format!(" ICON=\"{}\"", favicon);
-                let new_url = url.replace(">", &format!("{}>" , icon_tag));
-                processed_html.replace_range(mat.start() + offset..mat.end() + offset, &new_url);
-                offset += icon_tag.len();
+                processed += domain_bookmarks[&domain].len();
+                if let Ok(mut log_lock) = log.lock() {
+                    log_lock.push_str(&format!("[{:>3}/{}] {} {}... {}\n", processed, total, crate::i18n::get_message("fetching", None), domain, message));
+                }
+                favicons.insert(domain, icon.cloned());
+            }
+            None => {
+                match revalidation {
+                    Some((url, validators)) => revalidating.push((context, url.to_string(), validators)),
+                    None => pending.push(context),
+                }
+                if let Some(cached) = cached {
+                    expired.insert(domain, cached);
+                }
             }
+        }
+    }
+    if let Ok(mut progress_lock) = progress.lock() {
+        *progress_lock = (processed, total);
+    }
+
+    let concurrency = config.concurrency.max(1);
+    let svg_size = config.icon_size.pixels().unwrap_or(super::svg::DEFAULT_SIZE);
+    let mut aborted = false;
 
-            // 更新进度
-            processed += 1;
+    // 服务端返回 304 时继续使用缓存的图标并更新获取时间，图标已变化时直接使用返回的新图标，
+    // 请求失败时按正常流程重新获取
+    if !revalidating.is_empty() {
+        let client = super::client::build_client(&config.network)?;
+        let mut checks = stream::iter(revalidating)
+            .map(|(context, url, validators)| {
+                let client = &client;
+                let abort_flag = &abort_flag;
+                async move {
+                    let result = revalidate_async(client, &url, &validators, &config.network, abort_flag).await;
+                    (context, result)
+                }
+            })
+            .buffer_unordered(concurrency);
+        let mut not_modified = 0;
+        loop {
+            let Some(next) = until_aborted(&abort_flag, checks.next()).await else {
+                aborted = true;
+                break;
+            };
+            let Some((context, result)) = next else {
+                break;
+            };
+            let domain = context.domain.clone();
+            let Some(entry) = expired.get_mut(&domain) else {
+                pending.push(context);
+                continue;
+            };
+            let message = match result.and_then(|revalidation| match revalidation {
+                Revalidation::Modified(icon) => rasterize_svg(icon, svg_size).map(Revalidation::Modified),
+                Revalidation::NotModified => Ok(Revalidation::NotModified),
+            }) {
+                Ok(Revalidation::NotModified) => {
+                    entry.fetched_at = now;
+                    not_modified += 1;
+                    crate::i18n::get_message("not_modified", None)
+                }
+                // 新图标仍来自原来的服务
+                Ok(Revalidation::Modified(icon)) => {
+                    *entry = CacheEntry { provider: entry.provider.take(), ..CacheEntry::fetched(&icon, "") };
+                    format!("{} ({})", crate::i18n::get_message("success", None), entry.provider().unwrap_or_default())
+                }
+                Err(_) => {
+                    pending.push(context);
+                    continue;
+                }
+            };
+            put_cache(store, &domain, entry, &log);
+            processed += domain_bookmarks[&domain].len();
+            if let Ok(mut log_lock) = log.lock() {
+                log_lock.push_str(&format!("[{:>3}/{}] {} {}... {}\n", processed, total, crate::i18n::get_message("revalidating", None), domain, message));
+            }
             if let Ok(mut progress_lock) = progress.lock() {
                 *progress_lock = (processed, total);
             }
-            if processed % 10 == 0 || processed == total {
-                if let Ok(mut log_lock) = log.lock() {
-                    log_lock.push_str(&format!("{}: {}/{} ({:.1}%)\n", crate::i18n::get_message("processing", None),
-                        processed,
-                        total,
-                        (processed as f32 / total as f32) * 100.0
-                    ));
+            favicons.insert(domain, entry.icon().cloned());
+        }
+        if let Ok(mut log_lock) = log.lock() {
+            let mut args = HashMap::new();
+            args.insert("count".to_string(), not_modified.to_string());
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("not_modified_summary", Some(args))));
+        }
+    }
+
+    let mut fetches = stream::iter(pending)
+        .map(|context| {
+            let abort_flag = &abort_flag;
+            async move {
+                let result = fetch_from_chain(providers, &context, svg_size, abort_flag).await;
+                (context.domain, result)
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    let mut fetched = 0;
+    // 中止时未完成的请求随 stream 一起丢弃
+    while !aborted {
+        let Some(next) = until_aborted(&abort_flag, fetches.next()).await else {
+            aborted = true;
+            break;
+        };
+        let Some((domain, result)) = next else {
+            break;
+        };
+        let Some(result) = result else {
+            aborted = true;
+            break;
+        };
+        let stale = expired.remove(&domain).filter(|cached| cached.icon().is_some() && !cached.is_generated());
+        let (entry, message) = match result {
+            Ok((icon, provider)) => {
+                let message = format!("{} ({})", crate::i18n::get_message("success", None), provider);
+                (CacheEntry::fetched(&icon, provider), message)
+            }
+            // 过期的图标重新获取失败时继续使用，条目保持过期，下次处理时再次尝试
+            Err(e) => match stale {
+                Some(cached) => {
+                    let message = format!("{}: {} ({})", crate::i18n::get_message("failed", None), e, crate::i18n::get_message("stale_icon_kept", None));
+                    (cached, message)
                 }
+                None => {
+                    let message = format!("{}: {}", crate::i18n::get_message("failed", None), e);
+                    // 所有服务都失败时生成字母图标，缓存中标记为生成，下次处理时重新获取
+                    let generated = config.generate_fallback_icons
+                        .then(|| super::avatar::generate(&domain, svg_size).ok())
+                        .flatten();
+                    match generated {
+                        Some(icon) => (
+                            CacheEntry::generated(&icon, failure_kind(&e), e.to_string()),
+                            format!("{} ({})", message, crate::i18n::get_message("generated_icon", None)),
+                        ),
+                        None => (CacheEntry::failed(failure_kind(&e), e.to_string()), message),
+                    }
+                }
+            },
+        };
+        let favicon = entry.icon().cloned();
+        if entry.is_generated() {
+            generated_domains.insert(domain.clone());
+        }
+        put_cache(store, &domain, &entry, &log);
+
+        // 更新进度
+        let previous = processed;
+        processed += domain_bookmarks[&domain].len();
+        if let Ok(mut log_lock) = log.lock() {
+            log_lock.push_str(&format!("[{:>3}/{}] {} {}... {}\n", processed, total, crate::i18n::get_message("fetching", None), domain, message));
+            if processed / 10 != previous / 10 || processed == total {
+                log_lock.push_str(&format!("{}: {}/{} ({:.1}%)\n", crate::i18n::get_message("processing", None),
+                    processed,
+                    total,
+                    (processed as f32 / total as f32) * 100.0
+                ));
             }
+        }
+        if let Ok(mut progress_lock) = progress.lock() {
+            *progress_lock = (processed, total);
+        }
+        favicons.insert(domain, favicon);
+
+        // 每获取50个域名保存一次缓存，SQLite 存储已逐条写入
+        fetched += 1;
+        if fetched % 50 == 0 {
+            flush_cache(store, &log);
+        }
+    }
+    drop(fetches);
+
+    // 保存最终的缓存
+    flush_cache(store, &log);
 
-            // 每处理50个书签保存一次缓存
-            if processed - last_save >= 50 {
-                save_cache(&favicon_cache, &cache_path, &log);
-                last_save = processed;
+    // 3. 把结果写回书签
+    let mut success_count = 0;
+    let mut failed_count = 0;
+    let mut generated_count = 0;
+    for (domain, indices) in &domain_bookmarks {
+        match favicons.get(domain) {
+            Some(Some(favicon)) => {
+                if generated_domains.contains(domain) {
+                    failed_count += indices.len();
+                    generated_count += indices.len();
+                } else {
+                    success_count += indices.len();
+                }
+                for &index in indices {
+                    bookmarks[index].icon = Some(favicon.clone());
+                }
             }
+            Some(None) => failed_count += indices.len(),
+            None => {}
         }
     }
 
-    // 4. 保存更新后的HTML文件
-    if let Err(e) = fs::write(output, &processed_html) {
+    if aborted {
         if let Ok(mut log_lock) = log.lock() {
-            log_lock.push_str(&format!("Failed to save output file: {}\n", e));
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("processing_aborted_by_user", None)));
         }
-        return Err(AppError::FileError(e));
     }
 
-    // 5. 保存最终的缓存
-    save_cache(&favicon_cache, &cache_path, &log);
+    // 4. SVG 图标栅格化为 PNG，再按配置把图标转换为小尺寸 PNG
+    super::svg::rasterize_bookmarks(&mut bookmarks, svg_size);
+    let mut bytes_saved = 0;
+    if let (Some(size), false) = (config.icon_size.pixels(), aborted) {
+        let normalized = super::normalize::normalize_bookmarks(&mut bookmarks, size, &log);
+        bytes_saved = normalized.saved_bytes();
+        if let Ok(mut log_lock) = log.lock() {
+            let mut args = HashMap::new();
+            args.insert("count".to_string(), normalized.count.to_string());
+            args.insert("before".to_string(), crate::utils::format_size(normalized.original_bytes));
+            args.insert("after".to_string(), crate::utils::format_size(normalized.normalized_bytes));
+            args.insert("saved".to_string(), crate::utils::format_size(bytes_saved));
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("icons_normalized_summary", Some(args))));
+        }
+    }
+
+    Ok(ProcessStats {
+        success: success_count,
+        failed: failed_count,
+        generated: generated_count,
+        kept: kept_count,
+        total,
+        bytes_saved,
+        aborted,
+    })
+}
 
-    // 6. 完成处理
+/// 处理书签文件，为其中的链接添加favicon
+///
+/// 输入格式根据文件内容自动识别，结果按 `output_format` 写入 `output`
+pub async fn process_bookmarks(input: &str, output: &str, output_format: BookmarkFormat, config: &AppConfig, log: Arc<Mutex<String>>, abort_flag: Arc<AtomicBool>, progress: Arc<Mutex<(usize, usize)>>) -> AppResult<()> {
+    // 1. 识别书签文件格式
     if let Ok(mut log_lock) = log.lock() {
-        if abort_flag.load(Ordering::Relaxed) {
-            log_lock.push_str(&format!("\n[Stop] Saved: {}\nCompleted: {} bookmarks, Failed: {} bookmarks, Total: {} bookmarks\n",
-                output, success_count, failed_count, total));
-        } else {
-            {
+        log_lock.push_str("\n----------------------------------------\n");
+        log_lock.push_str(&format!("[{}] {}\n", Local::now().format("%Y-%m-%d %H:%M:%S"), crate::i18n::get_message("starting_to_process", None)));
+    }
+    let input_path = Path::new(input);
+    let input_format = match BookmarkFormat::detect(input_path) {
+        Ok(format) => format,
+        Err(e) => {
+            if let Ok(mut log_lock) = log.lock() {
+                log_lock.push_str(&format!("Failed to read file: {}\n", e));
+            }
+            return Err(e);
+        }
+    };
+
+    // 2. 解析书签树
+    let mut tree = match input_format.read(input_path) {
+        Ok(tree) => tree,
+        Err(e) => {
+            if let Ok(mut log_lock) = log.lock() {
+                log_lock.push_str(&format!("Failed to parse bookmarks file: {}\n", e));
+            }
+            return Err(e);
+        }
+    };
+    if let Ok(mut log_lock) = log.lock() {
+        log_lock.push_str(&format!("Successfully read bookmarks file ({})\n", input_format.name()));
+    }
+
+    // Firefox 已保存的图标直接补充到缓存，减少网络请求
+    if input_format == BookmarkFormat::FirefoxPlaces {
+        let seeded = open_store(&config.cache).and_then(|(mut store, migration)| {
+            let notices: Vec<String> = store.take_recovery().map(|recovery| recovery.message()).into_iter()
+                .chain(migration.map(|migration| migration.message()))
+                .collect();
+            let seeded = store.seed_from_tree(&tree)?;
+            store.flush()?;
+            Ok((seeded, notices))
+        });
+        if let Ok(mut log_lock) = log.lock() {
+            if let Ok((_, notices)) = &seeded {
+                for notice in notices {
+                    log_lock.push_str(&format!("{}\n", notice));
+                }
+            }
+            match seeded.map(|(seeded, _)| seeded) {
+                Ok(seeded) => log_lock.push_str(&format!("Seeded {} cached icons from favicons.sqlite\n", seeded)),
+                Err(e) => log_lock.push_str(&format!("Failed to write cache file: {}\n", e)),
+            }
+        }
+    }
+
+    // 3. 获取favicon
+    let stats = add_favicons(&mut tree, config, log.clone(), abort_flag, progress).await?;
+    if stats.aborted {
+        return Ok(());
+    }
+
+    // 4. 保存更新后的书签文件
+    if output_format == BookmarkFormat::Chromium {
+        let icons = tree.root.bookmarks().filter(|bookmark| bookmark.icon.is_some()).count();
+        if icons > 0 {
+            if let Ok(mut log_lock) = log.lock() {
                 let mut args = std::collections::HashMap::new();
-                args.insert("success".to_string(), success_count.to_string());
-                args.insert("failed".to_string(), failed_count.to_string());
-                args.insert("total".to_string(), total.to_string());
-                let summary = crate::i18n::get_message("processing_completed_summary", Some(args));
-                let mut path_args = std::collections::HashMap::new();
-                path_args.insert("path".to_string(), output.to_string());
-                let saved = crate::i18n::get_message("saved_to_path", Some(path_args));
-                log_lock.push_str(&format!("\n{}\n{}\n", summary, saved));
+                args.insert("count".to_string(), icons.to_string());
+                log_lock.push_str(&format!("{}\n", crate::i18n::get_message("chromium_icons_not_saved", Some(args))));
             }
+        }
+    }
+    let write_result = match output_format {
+        BookmarkFormat::FirefoxPlaces => crate::bookmarks::firefox::write_favicons(input_path, Path::new(output), &tree)
+            .map(|written| {
+                if let Ok(mut log_lock) = log.lock() {
+                    log_lock.push_str(&format!("Wrote {} icons to favicons.sqlite copy\n", written));
+                }
+            }),
+        format => format.write(&tree, Path::new(output)),
+    };
+    if let Err(e) = write_result {
+        if let Ok(mut log_lock) = log.lock() {
+            log_lock.push_str(&format!("Failed to save output file: {}\n", e));
+        }
+        return Err(e);
+    }
 
+    // 5. 完成处理，中止时已在上面返回，不会写入输出文件
+    if let Ok(mut log_lock) = log.lock() {
+        let mut args = std::collections::HashMap::new();
+        args.insert("success".to_string(), stats.success.to_string());
+        args.insert("failed".to_string(), stats.failed.to_string());
+        args.insert("total".to_string(), stats.total.to_string());
+        let summary = crate::i18n::get_message("processing_completed_summary", Some(args));
+        let mut path_args = std::collections::HashMap::new();
+        path_args.insert("path".to_string(), output.to_string());
+        let saved = crate::i18n::get_message("saved_to_path", Some(path_args));
+        log_lock.push_str(&format!("\n{}\n", summary));
+        if stats.generated > 0 {
+            let mut generated_args = std::collections::HashMap::new();
+            generated_args.insert("count".to_string(), stats.generated.to_string());
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("generated_icons_summary", Some(generated_args))));
+        }
+        if stats.kept > 0 {
+            let mut kept_args = std::collections::HashMap::new();
+            kept_args.insert("count".to_string(), stats.kept.to_string());
+            log_lock.push_str(&format!("{}\n", crate::i18n::get_message("kept_existing_icons", Some(kept_args))));
         }
+        log_lock.push_str(&format!("{}\n", saved));
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+    use std::time::{Duration, Instant};
+    use crate::bookmarks::Bookmark;
+    use crate::favicon::store::JsonStore;
+    use crate::favicon::test_server::{self, Response, TestServer};
+
+    const ETAG: &str = "\"v1\"";
+    const LAST_MODIFIED: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+    /// 永远不会返回的提供者，模拟卡住的请求
+    struct HangingProvider;
+
+    #[async_trait::async_trait]
+    impl FaviconProvider for HangingProvider {
+        fn name(&self) -> &str {
+            "hanging"
+        }
+
+        async fn fetch(&self, _context: &BookmarkContext) -> AppResult<Icon> {
+            std::future::pending().await
+        }
+    }
+
+    /// 总是返回同一个图标的提供者
+    struct StaticProvider;
+
+    #[async_trait::async_trait]
+    impl FaviconProvider for StaticProvider {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        async fn fetch(&self, _context: &BookmarkContext) -> AppResult<Icon> {
+            Icon::from_bytes(test_server::png())
+        }
+    }
+
+    #[tokio::test]
+    async fn processing_output_again_changes_nothing() {
+        let html = format!(
+            "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n<DL><p>\n\
+             <DT><A HREF=\"https://example.com/?a=1&amp;b=%3E\" ADD_DATE=\"1700000000\">Example &gt; page</A>\n\
+             <DT><A HREF=\"https://example.org/\" ICON=\"{}\">Existing icon</A>\n\
+             <DT><H3>Folder</H3>\n<DL><p>\n<DT><A HREF=\"https://example.net/\">Nested</A>\n</DL><p>\n\
+             </DL><p>\n",
+            Icon::from_bytes(test_server::png()).unwrap().to_data_url().replace("AAAA", "AAAB"),
+        );
+        let providers: Vec<Box<dyn FaviconProvider>> = vec![Box::new(StaticProvider)];
+        for icon_policy in crate::config::IconPolicy::ALL {
+            let config = AppConfig { icon_policy, ..AppConfig::default() };
+            let dir = tempfile::tempdir().unwrap();
+            let mut store = JsonStore::open(dir.path().join("cache.json").to_string_lossy());
+            let mut outputs = Vec::new();
+            let mut input = html.clone();
+            for _ in 0..2 {
+                let mut tree = crate::bookmarks::netscape::parse(&input).unwrap();
+                add_favicons_to_store(&mut tree, &config, &providers, &mut store,
+                    Arc::new(Mutex::new(String::new())), Arc::new(AtomicBool::new(false)), Arc::new(Mutex::new((0, 0)))).await.unwrap();
+                input = crate::bookmarks::netscape::to_html(&tree);
+                outputs.push(input.clone());
+            }
+            assert_eq!(outputs[0], outputs[1], "{:?}", icon_policy);
+            for line in outputs[0].lines().filter(|line| line.contains("HREF=")) {
+                assert_eq!(line.matches(" ICON=").count(), 1, "{:?}: {}", icon_policy, line);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn abort_interrupts_pending_provider() {
+        let providers: Vec<Box<dyn FaviconProvider>> = vec![Box::new(HangingProvider)];
+        let context = BookmarkContext { domain: "example.com".to_string(), url: "https://example.com/".to_string(), title: String::new() };
+        let abort_flag = Arc::new(AtomicBool::new(false));
+        let setter = abort_flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            setter.store(true, Ordering::Relaxed);
+        });
+
+        let started = Instant::now();
+        let result = tokio::time::timeout(Duration::from_secs(5), fetch_from_chain(&providers, &context, 32, &abort_flag)).await;
+        assert!(matches!(result, Ok(None)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    /// 已过期、带校验信息的缓存条目，图标与测试服务返回的不同
+    fn expired_entry(url: &str) -> CacheEntry {
+        let image = image::RgbaImage::from_pixel(1, 1, image::Rgba([0, 0, 0, 255]));
+        let mut data = Vec::new();
+        image.write_to(&mut std::io::Cursor::new(&mut data), image::ImageFormat::Png).unwrap();
+        let icon = Icon {
+            url: Some(url.to_string()),
+            etag: Some(ETAG.to_string()),
+            last_modified: Some(LAST_MODIFIED.to_string()),
+            ..Icon::from_bytes(data).unwrap()
+        };
+        CacheEntry { fetched_at: 0, ..CacheEntry::fetched(&icon, "Google") }
+    }
+
+    /// 缓存中有过期条目时处理一个书签，不配置任何服务，返回处理后的条目和统计
+    async fn revalidate(server: &TestServer) -> (CacheEntry, ProcessStats) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonStore::open(dir.path().join("cache.json").to_string_lossy());
+        store.put("example.com", &expired_entry(&server.url)).unwrap();
+        let mut tree = BookmarkTree::default();
+        tree.root.add_bookmark(Bookmark::new("Example", "https://example.com/"));
+
+        let stats = add_favicons_to_store(&mut tree, &AppConfig::default(), &[], &mut store,
+            Arc::new(Mutex::new(String::new())), Arc::new(AtomicBool::new(false)), Arc::new(Mutex::new((0, 0)))).await.unwrap();
+        (store.get("example.com").unwrap().unwrap(), stats)
+    }
+
+    #[tokio::test]
+    async fn not_modified_refreshes_cached_entry() {
+        let server = TestServer::start(vec![Response::new("304 Not Modified")]).await;
+        let started = chrono::Utc::now().timestamp();
+        let (entry, stats) = revalidate(&server).await;
+        assert!(entry.is_fresh(&AppConfig::default().cache, started));
+        assert!(entry.fetched_at >= started);
+        assert_eq!(entry.icon(), expired_entry(&server.url).icon());
+        assert_eq!(stats.success, 1);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let request = requests[0].to_lowercase();
+        assert!(request.contains(&format!("if-none-match: {}", ETAG).to_lowercase()));
+        assert!(request.contains(&format!("if-modified-since: {}", LAST_MODIFIED).to_lowercase()));
+    }
+
+    #[tokio::test]
+    async fn modified_icon_replaces_cached_entry() {
+        let server = TestServer::start(vec![Response::png().header("ETag", "\"v2\"")]).await;
+        let (entry, stats) = revalidate(&server).await;
+        let icon = Icon::from_bytes(test_server::png()).unwrap();
+        assert_eq!(entry.icon(), Some(&icon.to_data_url()));
+        assert_eq!(entry.provider(), Some("Google"));
+        assert_eq!(entry.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(stats.success, 1);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -0,0 +1,452 @@
+//! Favicon 提供者
+//!
+//! 每种获取方式实现 [`FaviconProvider`]，处理时按顺序依次尝试。
+//! 内置 URL 模板、直接从页面发现、本地目录和外部命令四种提供者，
+//! 库的使用者也可以实现该 trait 并通过 [`super::add_favicons_with_providers`] 使用自己的提供者
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::OnceCell;
+
+use crate::config::AppConfig;
+use crate::config::NetworkConfig;
+use crate::config::favicon_service::{FaviconService, ServiceKind};
+use crate::errors::{AppError, AppResult};
+use super::fetch::{fetch_body_async, fetch_icon_async, FailureKind};
+use super::icon::Icon;
+use super::placeholder::{content_hash, matches_placeholder, perceptual_hash};
+
+/// 探测占位图标时请求的域名，`.invalid` 顶级域名保证不存在
+const PROBE_DOMAIN: &str = "favicon-buddy-probe.invalid";
+/// 探测因临时性失败未得到结果时，一次处理中最多尝试的次数
+const MAX_PROBE_ATTEMPTS: u32 = 3;
+
+/// 获取图标时可用的书签信息，同一域名只取第一个书签
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkContext {
+    /// 书签域名
+    pub domain: String,
+    /// 书签地址
+    pub url: String,
+    /// 书签标题
+    pub title: String,
+}
+
+/// Favicon 提供者
+#[async_trait]
+pub trait FaviconProvider: Send + Sync {
+    /// 提供者名称，记录在缓存和日志中
+    fn name(&self) -> &str;
+
+    /// 获取书签的图标
+    ///
+    /// 失败时返回 [`AppError::FetchError`] 可以指明失败类型，临时性失败会在下次处理时重试
+    async fn fetch(&self, context: &BookmarkContext) -> AppResult<Icon>;
+}
+
+fn not_found(message: String) -> AppError {
+    AppError::FetchError { kind: FailureKind::NotFound, message }
+}
+
+/// 按 URL 模板请求第三方服务
+///
+/// 服务不认识域名时常返回 HTTP 200 的默认图标，与占位图标哈希匹配的结果按
+/// [`FailureKind::Placeholder`] 失败处理，以便换下一个提供者
+pub struct TemplateProvider {
+    name: String,
+    url_template: String,
+    client: reqwest::Client,
+    network: NetworkConfig,
+    placeholder_hashes: Vec<String>,
+    probe_placeholder: bool,
+    probed_hashes: OnceCell<Vec<String>>,
+    probe_failures: AtomicU32,
+}
+
+impl TemplateProvider {
+    /// 创建提供者，模板中的 `{domain}` 替换为书签域名
+    pub fn new(name: impl Into<String>, url_template: impl Into<String>, client: reqwest::Client, network: NetworkConfig) -> Self {
+        Self {
+            name: name.into(),
+            url_template: url_template.into(),
+            client,
+            network,
+            placeholder_hashes: Vec::new(),
+            probe_placeholder: false,
+            probed_hashes: OnceCell::new(),
+            probe_failures: AtomicU32::new(0),
+        }
+    }
+
+    /// 设置已知的占位图标哈希
+    pub fn with_placeholder_hashes(mut self, hashes: Vec<String>) -> Self {
+        self.placeholder_hashes = hashes;
+        self
+    }
+
+    /// 设置是否在第一次获取前请求一个不存在的域名，把返回的图标也视为占位图标
+    pub fn with_placeholder_probe(mut self, probe: bool) -> Self {
+        self.probe_placeholder = probe;
+        self
+    }
+
+    /// 探测服务的默认图标
+    ///
+    /// 无论状态码如何，返回的内容是图片时即为占位图标；不是图片时服务没有占位图标。
+    /// 超时、5xx 等临时性失败不缓存结果，之后的获取会再次探测，最多尝试 [`MAX_PROBE_ATTEMPTS`] 次
+    async fn probed_hashes(&self) -> &[String] {
+        if self.probe_failures.load(Ordering::Relaxed) >= MAX_PROBE_ATTEMPTS {
+            return &[];
+        }
+        let probed = self.probed_hashes.get_or_try_init(|| async {
+            let url = self.url_template.replace("{domain}", PROBE_DOMAIN);
+            let body = fetch_body_async(&self.client, &url).await?;
+            Ok::<_, AppError>(match Icon::from_bytes(body) {
+                Ok(icon) => std::iter::once(content_hash(&icon.data)).chain(perceptual_hash(&icon.data)).collect(),
+                Err(_) => Vec::new(),
+            })
+        }).await;
+        match probed {
+            Ok(hashes) => hashes,
+            Err(_) => {
+                self.probe_failures.fetch_add(1, Ordering::Relaxed);
+                &[]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl FaviconProvider for TemplateProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, context: &BookmarkContext) -> AppResult<Icon> {
+        let url = self.url_template.replace("{domain}", &context.domain);
+        let icon = fetch_icon_async(&self.client, &url, &self.network).await?;
+        let probed: &[String] = if self.probe_placeholder { self.probed_hashes().await } else { &[] };
+        if matches_placeholder(&icon.data, &self.placeholder_hashes) || matches_placeholder(&icon.data, probed) {
+            return Err(AppError::FetchError { kind: FailureKind::Placeholder, message: format!("Placeholder icon from {}", self.name) });
+        }
+        Ok(icon)
+    }
+}
+
+/// 直接请求书签页面，从页面声明中查找图标
+pub struct DirectProvider {
+    name: String,
+    client: reqwest::Client,
+    network: NetworkConfig,
+}
+
+impl DirectProvider {
+    /// 创建提供者
+    pub fn new(name: impl Into<String>, client: reqwest::Client, network: NetworkConfig) -> Self {
+        Self { name: name.into(), client, network }
+    }
+}
+
+#[async_trait]
+impl FaviconProvider for DirectProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, context: &BookmarkContext) -> AppResult<Icon> {
+        super::discover::fetch_direct(&self.client, &context.url, &self.network).await
+    }
+}
+
+/// 从本地目录读取图标
+///
+/// 文件名为域名加图片扩展名，如 `example.com.png`；找不到时再去掉 `www.` 前缀查找
+pub struct LocalDirectoryProvider {
+    name: String,
+    directory: PathBuf,
+}
+
+impl LocalDirectoryProvider {
+    /// 支持的图片扩展名，按优先顺序排列
+    const EXTENSIONS: [&'static str; 7] = ["png", "ico", "svg", "gif", "jpg", "jpeg", "webp"];
+
+    /// 创建提供者
+    pub fn new(name: impl Into<String>, directory: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), directory: directory.into() }
+    }
+
+    /// 按优先顺序列出可能的图标文件
+    fn candidates(&self, domain: &str) -> Vec<PathBuf> {
+        let mut names = vec![domain.to_string()];
+        if let Some(stripped) = domain.strip_prefix("www.") {
+            names.push(stripped.to_string());
+        }
+        names.iter()
+            .flat_map(|name| Self::EXTENSIONS.iter().map(move |ext| self.directory.join(format!("{}.{}", name, ext))))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl FaviconProvider for LocalDirectoryProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, context: &BookmarkContext) -> AppResult<Icon> {
+        for path in self.candidates(&context.domain) {
+            let data = match tokio::fs::read(&path).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if data.is_empty() {
+                continue;
+            }
+            return Icon::from_bytes(data);
+        }
+        Err(not_found(format!("No icon file for {} in {}", context.domain, self.directory.display())))
+    }
+}
+
+/// 调用外部命令获取图标
+///
+/// 命令行中的 `{domain}`、`{url}`、`{title}` 替换为书签信息，
+/// 命令的标准输出为图片数据或 data URL，退出码非零视为未找到图标
+pub struct CommandProvider {
+    name: String,
+    command_line: String,
+    timeout: Duration,
+}
+
+impl CommandProvider {
+    /// 创建提供者，命令运行超过 `timeout` 时终止
+    pub fn new(name: impl Into<String>, command_line: impl Into<String>, timeout: Duration) -> Self {
+        Self { name: name.into(), command_line: command_line.into(), timeout }
+    }
+
+    /// 拆分命令行并替换占位符，占位符在拆分后替换，书签信息中的空格和引号不会影响参数
+    ///
+    /// 书签标题等内容不可信，参数替换后以 `-` 开头而命令行中原本不是时拒绝运行，
+    /// 避免 `--output=...` 之类的标题被命令当作选项
+    fn args(&self, context: &BookmarkContext) -> AppResult<Vec<String>> {
+        split_command_line(&self.command_line).into_iter()
+            .map(|arg| {
+                let value = arg.replace("{domain}", &context.domain).replace("{url}", &context.url).replace("{title}", &context.title);
+                if value.starts_with('-') && !arg.starts_with('-') {
+                    return Err(AppError::FetchError {
+                        kind: FailureKind::Other,
+                        message: format!("Refusing to pass bookmark data as an option: {}", value),
+                    });
+                }
+                Ok(value)
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl FaviconProvider for CommandProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch(&self, context: &BookmarkContext) -> AppResult<Icon> {
+        let args = self.args(context)?;
+        let Some((program, args)) = args.split_first() else {
+            return Err(AppError::CustomError(format!("Empty command for provider {}", self.name)));
+        };
+        let child = tokio::process::Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let output = match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(output) => output?,
+            Err(_) => return Err(AppError::FetchError { kind: FailureKind::Timeout, message: format!("Command timed out: {}", program) }),
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(not_found(format!("Command exited with {}: {}", output.status, stderr.trim())));
+        }
+
+        let data = output.stdout;
+        if data.starts_with(b"data:") {
+            let url = String::from_utf8_lossy(&data);
+            return Icon::from_data_url(url.trim())
+                .ok_or_else(|| AppError::FetchError { kind: FailureKind::InvalidImage, message: "Invalid data URL".to_string() });
+        }
+        if data.is_empty() {
+            return Err(not_found(format!("Command returned no icon for {}", context.domain)));
+        }
+        Icon::from_bytes(data)
+    }
+}
+
+/// 按空白拆分命令行，支持单引号和双引号
+///
+/// 反斜杠只在引号前（单引号内除外）用于转义，其余位置按原样保留，以支持 `C:\Program Files\...` 这样的 Windows 路径
+fn split_command_line(command_line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = command_line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                let escaped = match (quote, chars.peek()) {
+                    (Some(_), Some('"')) | (None, Some('"' | '\'')) => chars.next(),
+                    _ => None,
+                };
+                current.push(escaped.unwrap_or('\\'));
+                in_arg = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// 根据服务配置创建提供者
+pub fn provider_from_service(service: &FaviconService, client: &reqwest::Client, network: &NetworkConfig) -> Box<dyn FaviconProvider> {
+    match service.kind {
+        ServiceKind::Template => Box::new(TemplateProvider::new(&service.name, &service.url_template, client.clone(), network.clone())
+            .with_placeholder_hashes(service.placeholder_hashes.clone())
+            .with_placeholder_probe(service.probe_placeholder)),
+        ServiceKind::Direct => Box::new(DirectProvider::new(&service.name, client.clone(), network.clone())),
+        ServiceKind::LocalDirectory => Box::new(LocalDirectoryProvider::new(&service.name, &service.url_template)),
+        ServiceKind::Command => Box::new(CommandProvider::new(&service.name, &service.url_template, Duration::from_secs(network.read_timeout_secs))),
+    }
+}
+
+/// 按配置中启用的服务顺序创建提供者
+pub fn providers_from_config(config: &AppConfig) -> AppResult<Vec<Box<dyn FaviconProvider>>> {
+    let client = super::client::build_client(&config.network)?;
+    Ok(config.favicon_service.chain()
+        .map(|service| provider_from_service(service, &client, &config.network))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::favicon::fetch::failure_kind;
+    use crate::favicon::test_server::{self, Response, TestServer};
+
+    fn context(title: &str) -> BookmarkContext {
+        BookmarkContext { domain: "example.com".to_string(), url: "https://example.com/a b".to_string(), title: title.to_string() }
+    }
+
+    fn provider(command_line: &str) -> CommandProvider {
+        CommandProvider::new("command", command_line, Duration::from_secs(5))
+    }
+
+    #[test]
+    fn placeholders_are_replaced_after_splitting() {
+        let args = provider("fetch-icon --name 'my icon' {url} \"{title}\"").args(&context("It's \"quoted\"")).unwrap();
+        assert_eq!(args, ["fetch-icon", "--name", "my icon", "https://example.com/a b", "It's \"quoted\""]);
+    }
+
+    #[test]
+    fn backslashes_are_kept_except_before_quotes() {
+        assert_eq!(split_command_line(r"C:\Tools\fetch.exe --out C:\icons\ {url}"), [r"C:\Tools\fetch.exe", "--out", r"C:\icons\", "{url}"]);
+        assert_eq!(split_command_line(r#""C:\Program Files\Fetch\fetch.exe" "{domain}""#), [r"C:\Program Files\Fetch\fetch.exe", "{domain}"]);
+        assert_eq!(split_command_line(r"'\\server\share\fetch.cmd' {url}"), [r"\\server\share\fetch.cmd", "{url}"]);
+    }
+
+    #[test]
+    fn quoted_arguments_are_joined() {
+        assert_eq!(split_command_line(r#"fetch --name="my icon" 'a "b"' "it's" \"x\" ''"#), ["fetch", "--name=my icon", r#"a "b""#, "it's", r#""x""#, ""]);
+        assert_eq!(split_command_line(r#"echo "say \"hi\"" don\'t"#), ["echo", r#"say "hi""#, "don't"]);
+        assert_eq!(split_command_line("  spaced\targs \n "), ["spaced", "args"]);
+    }
+
+    #[test]
+    fn bookmark_data_cannot_become_an_option() {
+        let error = provider("fetch-icon {title}").args(&context("--output=/etc/passwd")).unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::Other);
+        // 占位符不在参数开头时按原样传递
+        let args = provider("fetch-icon --title={title}").args(&context("-x")).unwrap();
+        assert_eq!(args, ["fetch-icon", "--title=-x"]);
+    }
+
+    fn template(server: &TestServer) -> TemplateProvider {
+        let network = NetworkConfig { max_retries: 0, ..NetworkConfig::default() };
+        TemplateProvider::new("template", format!("{}?domain={{domain}}", server.url), test_server::client(), network)
+            .with_placeholder_probe(true)
+    }
+
+    #[tokio::test]
+    async fn probe_hashes_default_icon_served_with_404() {
+        let server = TestServer::start(vec![
+            Response::png(),
+            Response::new("404 Not Found").header("Content-Type", "image/png").body(test_server::png()),
+        ]).await;
+        let error = template(&server).fetch(&context("")).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::Placeholder);
+        assert!(server.requests()[1].contains(PROBE_DOMAIN));
+    }
+
+    #[tokio::test]
+    async fn probe_without_image_means_no_placeholder() {
+        let server = TestServer::start(vec![
+            Response::png(),
+            Response::new("404 Not Found").header("Content-Type", "text/html").body("<html>Not found</html>"),
+            Response::png(),
+        ]).await;
+        let provider = template(&server);
+        assert!(provider.fetch(&context("")).await.is_ok());
+        // 探测结果已缓存，第二次获取不再探测
+        assert!(provider.fetch(&context("")).await.is_ok());
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn failed_probe_is_retried() {
+        let server = TestServer::start(vec![
+            Response::png(),
+            Response::new("503 Service Unavailable"),
+            Response::png(),
+            Response::new("404 Not Found").body(test_server::png()),
+        ]).await;
+        let provider = template(&server);
+        assert!(provider.fetch(&context("")).await.is_ok());
+        let error = provider.fetch(&context("")).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::Placeholder);
+    }
+
+    #[tokio::test]
+    async fn configured_hashes_match_without_probe() {
+        let server = TestServer::start(vec![Response::png()]).await;
+        let provider = template(&server)
+            .with_placeholder_probe(false)
+            .with_placeholder_hashes(vec![content_hash(&test_server::png())]);
+        let error = provider.fetch(&context("")).await.unwrap_err();
+        assert_eq!(failure_kind(&error), FailureKind::Placeholder);
+    }
+}
@@ -0,0 +1,569 @@
+//! 缓存存储
+//!
+//! [`CacheStore`] 抽象缓存条目的读写，JSON 文件和嵌入式 SQLite 数据库是两种实现。
+//! 打开缓存时如果发现另一种存储方式留下的缓存，自动迁移过来
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::bookmarks::BookmarkTree;
+use crate::config::{CacheBackend, CacheConfig};
+use crate::errors::{AppError, AppResult};
+use crate::utils::{data_url, Recovery};
+use crate::utils::file::backup_path;
+use super::cache::{blob_hash, get_cache_db_path, get_cache_path, CacheEntry, FaviconCache};
+use super::fetch::FailureKind;
+use super::icon::Icon;
+use super::placeholder::is_placeholder;
+
+/// 缓存存储后端，key 为域名
+pub trait CacheStore: Send {
+    /// 读取域名的缓存条目
+    fn get(&self, domain: &str) -> AppResult<Option<CacheEntry>>;
+
+    /// 写入或替换域名的缓存条目
+    fn put(&mut self, domain: &str, entry: &CacheEntry) -> AppResult<()>;
+
+    /// 批量写入缓存条目，默认逐条写入
+    fn put_all(&mut self, entries: Vec<(String, CacheEntry)>) -> AppResult<()> {
+        for (domain, entry) in &entries {
+            self.put(domain, entry)?;
+        }
+        Ok(())
+    }
+
+    /// 读取所有缓存条目，用于导出和迁移
+    fn entries(&self) -> AppResult<Vec<(String, CacheEntry)>>;
+
+    /// 缓存条目数
+    fn len(&self) -> AppResult<usize>;
+
+    /// 缓存是否为空
+    fn is_empty(&self) -> AppResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// 把尚未写入磁盘的修改保存下来
+    fn flush(&mut self) -> AppResult<()>;
+
+    /// 打开时缓存文件已损坏的处理结果，取出后不再返回
+    fn take_recovery(&mut self) -> Option<Recovery> {
+        None
+    }
+
+    /// 用书签已带有的 data URL 图标补充缓存
+    ///
+    /// 优先使用 `ICON`，其次是本身为 data URL 的 `ICON_URI`；远程的 `ICON_URI` 需要联网获取，不在此处理。
+    /// 无法解码的图片和占位图标会被跳过。
+    /// 只填充缺失、上次获取失败或只有生成图标的域名，已有图标的域名保持不变。返回新增图标的域名数
+    fn seed_from_tree(&mut self, tree: &BookmarkTree) -> AppResult<usize> {
+        let mut seeded = HashMap::new();
+        for bookmark in tree.bookmarks() {
+            let icon = [bookmark.icon.as_ref(), bookmark.icon_uri.as_ref()].into_iter()
+                .flatten()
+                .filter(|icon| icon.starts_with("data:") && !is_placeholder(icon))
+                .find_map(|icon| Icon::from_data_url(icon));
+            let (Some(domain), Some(icon)) = (bookmark.domain(), icon) else {
+                continue;
+            };
+            if seeded.contains_key(&domain) {
+                continue;
+            }
+            let replace = self.get(&domain)?.is_none_or(|entry| entry.icon().is_none() || entry.is_generated());
+            if replace {
+                seeded.insert(domain, CacheEntry::imported(icon.to_data_url()));
+            }
+        }
+        let count = seeded.len();
+        self.put_all(seeded.into_iter().collect())?;
+        Ok(count)
+    }
+}
+
+/// JSON 文件存储
+///
+/// 所有条目都在内存中，[`CacheStore::flush`] 时整体写入文件
+pub struct JsonStore {
+    path: String,
+    cache: FaviconCache,
+    dirty: bool,
+    recovery: Option<Recovery>,
+}
+
+impl JsonStore {
+    /// 打开 JSON 缓存文件，文件无法解析时改用备份，都不可用时为空缓存
+    pub fn open(path: impl Into<String>) -> Self {
+        let path = path.into();
+        let (cache, recovery) = FaviconCache::load(&path);
+        Self { path, cache, dirty: false, recovery }
+    }
+}
+
+impl CacheStore for JsonStore {
+    fn get(&self, domain: &str) -> AppResult<Option<CacheEntry>> {
+        Ok(self.cache.0.get(domain).cloned())
+    }
+
+    fn put(&mut self, domain: &str, entry: &CacheEntry) -> AppResult<()> {
+        self.cache.0.insert(domain.to_string(), entry.clone());
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn entries(&self) -> AppResult<Vec<(String, CacheEntry)>> {
+        Ok(self.cache.0.iter().map(|(domain, entry)| (domain.clone(), entry.clone())).collect())
+    }
+
+    fn len(&self) -> AppResult<usize> {
+        Ok(self.cache.0.len())
+    }
+
+    fn flush(&mut self) -> AppResult<()> {
+        if self.dirty {
+            self.cache.save(&self.path)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn take_recovery(&mut self) -> Option<Recovery> {
+        self.recovery.take()
+    }
+}
+
+/// SQLite 数据库存储
+///
+/// 每个域名一行，按域名主键查找，每次写入立即提交，写入中途崩溃时由 SQLite 日志保证不会损坏。图标以二进制按 SHA-256 哈希保存在 `icons` 表中，
+/// 内容相同的图标只保存一次，各域名引用其哈希，读取时再编码为 data URL；不再被任何域名引用的图标随替换它的写入一起删除
+pub struct SqliteStore {
+    conn: Connection,
+    recovery: Option<Recovery>,
+}
+
+const PRAGMAS: &str = "
+    PRAGMA journal_mode = WAL;
+    PRAGMA synchronous = NORMAL;
+";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS icons (
+        hash TEXT PRIMARY KEY NOT NULL,
+        data BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS entries (
+        domain TEXT PRIMARY KEY NOT NULL,
+        fetched_at INTEGER NOT NULL,
+        icon_hash TEXT,
+        provider TEXT,
+        mime TEXT,
+        size INTEGER,
+        hash TEXT,
+        url TEXT,
+        etag TEXT,
+        last_modified TEXT,
+        failure TEXT,
+        failure_reason TEXT,
+        generated INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX IF NOT EXISTS entries_icon_hash ON entries (icon_hash);
+";
+
+const COLUMNS: &str = "domain, fetched_at, icon_hash, provider, mime, size, hash, url, etag, last_modified, failure, failure_reason, generated";
+
+const SELECT: &str = "SELECT e.domain, e.fetched_at, i.data AS icon, e.provider, e.mime, e.size, e.hash, e.url, e.etag, e.last_modified, \
+    e.failure, e.failure_reason, e.generated FROM entries e LEFT JOIN icons i ON i.hash = e.icon_hash";
+
+impl SqliteStore {
+    /// 打开或创建缓存数据库，并删除不再被引用的图标
+    ///
+    /// 数据库文件已损坏、不是 SQLite 数据库或完整性检查失败时，把它改名为 `*.corrupt` 后重建，
+    /// 处理结果可通过 [`CacheStore::take_recovery`] 取出；其他错误（如没有权限、磁盘已满）直接返回
+    pub fn open(path: impl AsRef<Path>) -> AppResult<Self> {
+        let path = path.as_ref();
+        match Self::open_checked(path) {
+            Ok(conn) => Ok(Self { conn, recovery: None }),
+            Err(e) if is_corrupt(&e) => {
+                for suffix in ["", "-wal", "-shm"] {
+                    let mut file = path.as_os_str().to_owned();
+                    file.push(suffix);
+                    let mut corrupt = path.as_os_str().to_owned();
+                    corrupt.push(format!(".corrupt{}", suffix));
+                    match fs::rename(&file, &corrupt) {
+                        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                }
+                let conn = Self::open_checked(path)?;
+                let recovery = Recovery { path: path.to_path_buf(), error: e.to_string(), restored: false };
+                Ok(Self { conn, recovery: Some(recovery) })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn open_checked(path: &Path) -> AppResult<Connection> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(PRAGMAS)?;
+        let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if check != "ok" {
+            let error = rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT);
+            return Err(rusqlite::Error::SqliteFailure(error, Some(check)).into());
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.execute("DELETE FROM icons WHERE hash NOT IN (SELECT icon_hash FROM entries WHERE icon_hash IS NOT NULL)", [])?;
+        Ok(conn)
+    }
+
+    /// 写入一个条目，应在事务中调用，以便与删除原图标一起提交
+    fn insert(conn: &Connection, domain: &str, entry: &CacheEntry) -> AppResult<()> {
+        // 图标保存为解码后的图片数据，`mime` 列为 NULL 时保存的是无法解码的原始字符串
+        let (icon, mime) = match entry.icon.as_deref() {
+            Some(icon) => match data_url::decode(icon) {
+                Some((mime, data)) => (Some(data), Some(mime)),
+                None => (Some(icon.as_bytes().to_vec()), None),
+            },
+            None => (None, entry.mime.clone()),
+        };
+        let previous: Option<String> = conn.query_row("SELECT icon_hash FROM entries WHERE domain = ?1", params![domain], |row| row.get(0))
+            .optional()?
+            .flatten();
+        let icon_hash = match icon {
+            Some(data) => {
+                let key = blob_hash(&data);
+                conn.execute("INSERT OR IGNORE INTO icons (hash, data) VALUES (?1, ?2)", params![key, data])?;
+                Some(key)
+            }
+            None => None,
+        };
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO entries ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)", COLUMNS),
+            params![
+                domain,
+                entry.fetched_at,
+                icon_hash,
+                entry.provider,
+                mime,
+                entry.size.map(|size| size as i64),
+                entry.hash,
+                entry.url,
+                entry.etag,
+                entry.last_modified,
+                entry.failure.and_then(failure_name),
+                entry.failure_reason,
+                entry.generated,
+            ],
+        )?;
+        // 原来的图标不再被任何域名引用时删除
+        if let Some(previous) = previous.filter(|previous| icon_hash.as_ref() != Some(previous)) {
+            conn.execute("DELETE FROM icons WHERE hash = ?1 AND NOT EXISTS (SELECT 1 FROM entries WHERE icon_hash = ?1)", params![previous])?;
+        }
+        Ok(())
+    }
+}
+
+/// 失败类型保存为与 JSON 缓存相同的名称
+fn failure_name(failure: FailureKind) -> Option<String> {
+    serde_json::to_value(failure).ok()?.as_str().map(str::to_string)
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<(String, CacheEntry)> {
+    let mime: Option<String> = row.get("mime")?;
+    let icon: Option<Vec<u8>> = row.get("icon")?;
+    let failure: Option<String> = row.get("failure")?;
+    let entry = CacheEntry {
+        fetched_at: row.get("fetched_at")?,
+        icon: icon.map(|data| match &mime {
+            Some(mime) => data_url::encode(mime, &data),
+            None => String::from_utf8_lossy(&data).into_owned(),
+        }),
+        provider: row.get("provider")?,
+        mime,
+        size: row.get::<_, Option<i64>>("size")?.map(|size| size as usize),
+        hash: row.get("hash")?,
+        url: row.get("url")?,
+        etag: row.get("etag")?,
+        last_modified: row.get("last_modified")?,
+        failure: failure.and_then(|name| serde_json::from_value(serde_json::Value::String(name)).ok()),
+        failure_reason: row.get("failure_reason")?,
+        generated: row.get("generated")?,
+    };
+    Ok((row.get("domain")?, entry))
+}
+
+impl CacheStore for SqliteStore {
+    fn get(&self, domain: &str) -> AppResult<Option<CacheEntry>> {
+        let sql = format!("{} WHERE e.domain = ?1", SELECT);
+        let entry = self.conn.query_row(&sql, params![domain], entry_from_row).optional()?;
+        Ok(entry.map(|(_, entry)| entry))
+    }
+
+    fn put(&mut self, domain: &str, entry: &CacheEntry) -> AppResult<()> {
+        let tx = self.conn.transaction()?;
+        Self::insert(&tx, domain, entry)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 在一个事务中写入所有条目
+    fn put_all(&mut self, entries: Vec<(String, CacheEntry)>) -> AppResult<()> {
+        let tx = self.conn.transaction()?;
+        for (domain, entry) in &entries {
+            Self::insert(&tx, domain, entry)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn entries(&self) -> AppResult<Vec<(String, CacheEntry)>> {
+        let mut stmt = self.conn.prepare(SELECT)?;
+        let entries = stmt.query_map([], entry_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    fn len(&self) -> AppResult<usize> {
+        let count: i64 = self.conn.query_row("SELECT count(*) FROM entries", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// 每次写入都已提交，无需额外保存
+    fn flush(&mut self) -> AppResult<()> {
+        Ok(())
+    }
+
+    fn take_recovery(&mut self) -> Option<Recovery> {
+        self.recovery.take()
+    }
+}
+
+/// 存储方式对应的缓存文件路径
+/// 是否为数据库文件损坏或不是 SQLite 数据库导致的错误
+fn is_corrupt(error: &AppError) -> bool {
+    matches!(error, AppError::DatabaseError(rusqlite::Error::SqliteFailure(e, _))
+        if matches!(e.code, rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase))
+}
+
+fn backend_path(backend: CacheBackend) -> String {
+    match backend {
+        CacheBackend::Json => get_cache_path(),
+        CacheBackend::Sqlite => get_cache_db_path(),
+    }
+}
+
+fn open_backend(backend: CacheBackend, path: String) -> AppResult<Box<dyn CacheStore>> {
+    Ok(match backend {
+        CacheBackend::Json => Box::new(JsonStore::open(path)),
+        CacheBackend::Sqlite => Box::new(SqliteStore::open(path)?),
+    })
+}
+
+/// 从另一种存储方式迁移缓存的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    /// 原缓存文件
+    pub from: String,
+    /// 当前使用的缓存文件
+    pub to: String,
+    /// 原缓存文件改名后的路径
+    pub renamed: String,
+    /// 迁移的条目数
+    pub count: usize,
+}
+
+impl Migration {
+    /// 说明迁移结果的日志消息
+    pub fn message(&self) -> String {
+        let mut args = HashMap::new();
+        args.insert("from".to_string(), self.from.clone());
+        args.insert("to".to_string(), self.to.clone());
+        args.insert("renamed".to_string(), self.renamed.clone());
+        args.insert("count".to_string(), self.count.to_string());
+        crate::i18n::get_message("cache_migrated", Some(args))
+    }
+}
+
+/// 打开配置的缓存存储
+///
+/// 当前存储为空而另一种存储方式的缓存文件存在时，把其中的条目迁移过来，原文件和它的 `.bak` 备份改名为 `*.migrated`，
+/// 并返回 [`Migration`] 供调用方记录
+pub fn open_store(config: &CacheConfig) -> AppResult<(Box<dyn CacheStore>, Option<Migration>)> {
+    open_store_with(config.backend, &backend_path)
+}
+
+fn open_store_with(backend: CacheBackend, path_of: &dyn Fn(CacheBackend) -> String) -> AppResult<(Box<dyn CacheStore>, Option<Migration>)> {
+    let path = path_of(backend);
+    // 缓存之前已迁移到另一种存储方式，残留的备份是迁移前的旧数据，不能用来恢复，应从另一种存储方式迁移回来
+    if !Path::new(&path).exists() && Path::new(&migrated_path(&path)).exists() {
+        retire_backup(&path)?;
+    }
+    let mut store = open_backend(backend, path.clone())?;
+    let mut migration = None;
+    for other in CacheBackend::ALL.into_iter().filter(|other| *other != backend) {
+        let from = path_of(other);
+        if !Path::new(&from).exists() || !store.is_empty()? {
+            continue;
+        }
+        let entries = open_backend(other, from.clone())?.entries()?;
+        let count = entries.len();
+        store.put_all(entries)?;
+        store.flush()?;
+        let renamed = migrated_path(&from);
+        fs::rename(&from, &renamed)?;
+        retire_backup(&from)?;
+        migration = Some(Migration { from, to: path.clone(), renamed, count });
+    }
+    Ok((store, migration))
+}
+
+fn migrated_path(path: &str) -> String {
+    format!("{}.migrated", path)
+}
+
+/// 把缓存文件的 `.bak` 备份改名为 `*.migrated.bak`，避免之后打开时被当作当前缓存恢复
+fn retire_backup(path: &str) -> AppResult<()> {
+    match fs::rename(backup_path(Path::new(path)), format!("{}.bak", migrated_path(path))) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bookmarks::Bookmark;
+
+    fn icon(byte: u8) -> Icon {
+        Icon::new("image/png", vec![byte; 4])
+    }
+
+    #[test]
+    fn seeding_skips_invalid_and_placeholder_icons() {
+        let valid = Icon::from_bytes(super::super::test_server::png()).unwrap().to_data_url();
+        let blank = image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 255, 255, 255]));
+        let mut data = Vec::new();
+        blank.write_to(&mut std::io::Cursor::new(&mut data), image::ImageFormat::Png).unwrap();
+        let placeholder = Icon::from_bytes(data).unwrap().to_data_url();
+
+        let mut tree = BookmarkTree::default();
+        for (url, icon) in [
+            ("https://valid.test/", valid.as_str()),
+            ("https://invalid.test/", "data:image/png;base64,bm90IGFuIGltYWdl"),
+            ("https://placeholder.test/", placeholder.as_str()),
+        ] {
+            let mut bookmark = Bookmark::new(url, url);
+            bookmark.icon = Some(icon.to_string());
+            tree.root.add_bookmark(bookmark);
+        }
+        // ICON 无效时使用 data URL 形式的 ICON_URI
+        let mut bookmark = Bookmark::new("Fallback", "https://fallback.test/");
+        bookmark.icon = Some(placeholder.clone());
+        bookmark.icon_uri = Some(valid.clone());
+        tree.root.add_bookmark(bookmark);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonStore::open(dir.path().join("cache.json").to_string_lossy());
+        assert_eq!(store.seed_from_tree(&tree).unwrap(), 2);
+        assert_eq!(store.get("valid.test").unwrap().unwrap().icon(), Some(&valid));
+        assert_eq!(store.get("fallback.test").unwrap().unwrap().icon(), Some(&valid));
+        assert!(store.get("invalid.test").unwrap().is_none());
+        assert!(store.get("placeholder.test").unwrap().is_none());
+    }
+
+    #[test]
+    fn switching_backends_back_and_forth_keeps_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("cache.json").to_string_lossy().into_owned();
+        let sqlite = dir.path().join("cache.sqlite").to_string_lossy().into_owned();
+        let path_of = |backend| match backend {
+            CacheBackend::Json => json.clone(),
+            CacheBackend::Sqlite => sqlite.clone(),
+        };
+
+        // 保存两次，留下只有 a.test 的 .bak
+        let mut store = JsonStore::open(json.clone());
+        store.put("a.test", &CacheEntry::fetched(&icon(1), "Google")).unwrap();
+        store.flush().unwrap();
+        store.put("b.test", &CacheEntry::fetched(&icon(2), "Google")).unwrap();
+        store.flush().unwrap();
+        assert!(backup_path(Path::new(&json)).exists());
+
+        let (mut store, migration) = open_store_with(CacheBackend::Sqlite, &path_of).unwrap();
+        let migration = migration.unwrap();
+        assert_eq!((migration.from.as_str(), migration.count), (json.as_str(), 2));
+        assert!(!Path::new(&json).exists());
+        assert!(!backup_path(Path::new(&json)).exists());
+        assert!(Path::new(&migrated_path(&json)).exists());
+        store.put("c.test", &CacheEntry::fetched(&icon(3), "Google")).unwrap();
+        drop(store);
+
+        // 旧版本迁移时留下的 .bak 不会被恢复
+        fs::copy(migrated_path(&json), backup_path(Path::new(&json))).unwrap();
+        let (mut store, migration) = open_store_with(CacheBackend::Json, &path_of).unwrap();
+        assert!(store.take_recovery().is_none());
+        assert_eq!(migration.unwrap().count, 3);
+        assert_eq!(store.len().unwrap(), 3);
+        assert_eq!(store.get("c.test").unwrap().unwrap().icon(), Some(&icon(3).to_data_url()));
+        assert!(!Path::new(&sqlite).exists());
+        drop(store);
+
+        let (store, migration) = open_store_with(CacheBackend::Sqlite, &path_of).unwrap();
+        assert_eq!(migration.unwrap().count, 3);
+        assert_eq!(store.len().unwrap(), 3);
+    }
+
+    fn icon_hashes(store: &SqliteStore) -> Vec<String> {
+        let mut stmt = store.conn.prepare("SELECT hash FROM icons ORDER BY hash").unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn replaced_icons_are_deleted_once_unreferenced() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SqliteStore::open(dir.path().join("cache.sqlite")).unwrap();
+        store.put("a.test", &CacheEntry::fetched(&icon(1), "Google")).unwrap();
+        store.put("b.test", &CacheEntry::fetched(&icon(1), "Google")).unwrap();
+        assert_eq!(icon_hashes(&store), [blob_hash(&[1; 4])]);
+
+        // 仍被 b.test 引用的图标保留
+        store.put("a.test", &CacheEntry::fetched(&icon(2), "Google")).unwrap();
+        assert_eq!(icon_hashes(&store).len(), 2);
+
+        store.put("b.test", &CacheEntry::failed(FailureKind::NotFound, "HTTP 404")).unwrap();
+        assert_eq!(icon_hashes(&store), [blob_hash(&[2; 4])]);
+        assert_eq!(store.get("a.test").unwrap().unwrap().icon(), Some(&icon(2).to_data_url()));
+    }
+
+    #[test]
+    fn damaged_database_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.sqlite");
+        fs::write(&path, vec![0x5a; 8192]).unwrap();
+        let mut store = SqliteStore::open(&path).unwrap();
+        let recovery = store.take_recovery().unwrap();
+        assert_eq!(recovery.path, path);
+        assert!(!recovery.restored);
+        assert!(store.is_empty().unwrap());
+        // 损坏的文件改名保留
+        assert_eq!(fs::read(dir.path().join("cache.sqlite.corrupt")).unwrap(), vec![0x5a; 8192]);
+        store.put("a.test", &CacheEntry::fetched(&icon(1), "Google")).unwrap();
+        drop(store);
+
+        let mut store = SqliteStore::open(&path).unwrap();
+        assert!(store.take_recovery().is_none());
+        assert_eq!(store.len().unwrap(), 1);
+    }
+
+    #[test]
+    fn other_open_errors_are_returned() {
+        let dir = tempfile::tempdir().unwrap();
+        // 路径是目录，无法作为数据库打开，但数据本身没有损坏
+        let path = dir.path().join("cache.sqlite");
+        fs::create_dir(&path).unwrap();
+        assert!(SqliteStore::open(&path).is_err());
+        assert!(path.is_dir());
+        assert!(!dir.path().join("cache.sqlite.corrupt").exists());
+    }
+}
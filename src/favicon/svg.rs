@@ -0,0 +1,222 @@
+//! SVG 图标栅格化
+//!
+//! 部分浏览器导入书签时不接受 SVG 图标，先清理脚本、事件属性和外部引用，
+//! 再用 resvg 在本地渲染为 PNG，不发起任何网络请求
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+
+use image::error::{DecodingError, ImageFormatHint};
+use image::{DynamicImage, ImageError, Rgba, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use roxmltree::{Document, Node, NodeType};
+
+use crate::bookmarks::Bookmark;
+use crate::errors::AppResult;
+use crate::utils::data_url;
+use crate::utils::image::sniff_mime;
+use super::icon::Icon;
+use super::normalize::encode_png;
+
+/// 未配置图标尺寸时的栅格化尺寸
+pub const DEFAULT_SIZE: u32 = 32;
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// 会执行脚本或嵌入外部内容的元素
+const BLOCKED_ELEMENTS: [&str; 8] = ["script", "foreignObject", "iframe", "object", "embed", "audio", "video", "handler"];
+
+fn svg_error(message: impl ToString) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("SVG".to_string()), message.to_string()))
+}
+
+/// 清理 SVG，返回只包含 SVG 元素的安全文档
+///
+/// 删除脚本等元素、`on*` 事件属性、指向外部的 `href` 和 `url(...)` 引用、注释和处理指令，
+/// 其他命名空间的元素和属性（编辑器元数据等）一并删除
+pub fn sanitize(data: &[u8]) -> AppResult<String> {
+    let text = std::str::from_utf8(data).map_err(svg_error)?;
+    let text = text.trim_start_matches('\u{feff}');
+    let document = Document::parse(text).map_err(svg_error)?;
+    let root = document.root_element();
+    if !is_svg_element(&root) || root.tag_name().name() != "svg" {
+        return Err(svg_error("Root element is not <svg>").into());
+    }
+    let mut output = String::new();
+    write_element(&root, true, &mut output);
+    Ok(output)
+}
+
+fn is_svg_element(node: &Node) -> bool {
+    matches!(node.tag_name().namespace(), None | Some(SVG_NS))
+}
+
+/// 引用是否指向文档内部或内嵌的图片数据
+fn is_local_reference(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with('#') || value.to_ascii_lowercase().starts_with("data:image/")
+}
+
+/// 样式中的 `url(...)` 和 `@import` 是否都指向文档内部
+fn is_local_style(value: &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    if lower.contains("@import") {
+        return false;
+    }
+    lower.match_indices("url(").all(|(index, _)| {
+        let target = lower[index + 4..].trim_start().trim_start_matches(['"', '\'']);
+        target.starts_with('#')
+    })
+}
+
+fn escape(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            c => output.push(c),
+        }
+    }
+}
+
+fn write_element(node: &Node, is_root: bool, output: &mut String) {
+    let name = node.tag_name().name();
+    output.push('<');
+    output.push_str(name);
+    if is_root {
+        let _ = write!(output, " xmlns=\"{}\" xmlns:xlink=\"{}\"", SVG_NS, XLINK_NS);
+    }
+    for attribute in node.attributes() {
+        let prefix = match attribute.namespace() {
+            None => "",
+            Some(XLINK_NS) => "xlink:",
+            Some(XML_NS) => "xml:",
+            Some(_) => continue,
+        };
+        let attr_name = attribute.name();
+        let value = attribute.value();
+        if attr_name.to_ascii_lowercase().starts_with("on") {
+            continue;
+        }
+        if attr_name == "href" && !is_local_reference(value) {
+            continue;
+        }
+        if !is_local_style(value) {
+            continue;
+        }
+        let _ = write!(output, " {}{}=\"", prefix, attr_name);
+        escape(value, output);
+        output.push('"');
+    }
+    output.push('>');
+
+    for child in node.children() {
+        match child.node_type() {
+            NodeType::Element => {
+                if !is_svg_element(&child) || BLOCKED_ELEMENTS.contains(&child.tag_name().name()) {
+                    continue;
+                }
+                if child.tag_name().name() == "style" && !child.text().is_none_or(is_local_style) {
+                    continue;
+                }
+                write_element(&child, false, output);
+            }
+            NodeType::Text => escape(child.text().unwrap_or_default(), output),
+            _ => {}
+        }
+    }
+    let _ = write!(output, "</{}>", name);
+}
+
+/// 本机字体，第一次渲染文字时加载
+fn system_fonts() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut database = usvg::fontdb::Database::new();
+        database.load_system_fonts();
+        Arc::new(database)
+    }).clone()
+}
+
+/// 清理并渲染 SVG 为 `size`×`size` 的 PNG，保持宽高比居中放置
+pub fn rasterize(data: &[u8], size: u32) -> AppResult<Icon> {
+    let svg = sanitize(data)?;
+    let mut options = usvg::Options {
+        fontdb: system_fonts(),
+        ..Default::default()
+    };
+    // 清理后仍不读取任何外部文件
+    options.image_href_resolver.resolve_string = Box::new(|_, _| None);
+    let tree = usvg::Tree::from_str(&svg, &options).map_err(svg_error)?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or_else(|| svg_error("Invalid icon size"))?;
+    let tree_size = tree.size();
+    let scale = size as f32 / tree_size.width().max(tree_size.height());
+    let transform = tiny_skia::Transform::from_translate(
+        (size as f32 - tree_size.width() * scale) / 2.0,
+        (size as f32 - tree_size.height() * scale) / 2.0,
+    ).pre_scale(scale, scale);
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let pixels = pixmap.pixels();
+    let image = RgbaImage::from_fn(size, size, |x, y| {
+        let color = pixels[(y * size + x) as usize].demultiply();
+        Rgba([color.red(), color.green(), color.blue(), color.alpha()])
+    });
+    Ok(Icon::new("image/png", encode_png(DynamicImage::ImageRgba8(image))?))
+}
+
+/// 把书签中的 SVG 图标栅格化为 `size`×`size` 的 PNG，返回转换的书签数
+///
+/// 缓存、已有书签文件和 Firefox 数据库中的图标可能是 SVG，写入书签前统一转换；
+/// 同时支持 base64 和文本形式的 data URL，无法渲染的图标保持不变
+pub fn rasterize_bookmarks(bookmarks: &mut [&mut Bookmark], size: u32) -> usize {
+    let mut converted: HashMap<String, Option<String>> = HashMap::new();
+    let mut count = 0;
+    for bookmark in bookmarks.iter_mut() {
+        let Some(icon) = bookmark.icon.as_ref() else {
+            continue;
+        };
+        let rasterized = converted.entry(icon.clone())
+            .or_insert_with(|| {
+                let (mime, data) = data_url::decode_any(icon)?;
+                let is_svg = mime == "image/svg+xml" || sniff_mime(&data) == Some("image/svg+xml");
+                is_svg.then(|| rasterize(&data, size).ok()).flatten().map(|png| png.to_data_url())
+            })
+            .clone();
+        if let Some(rasterized) = rasterized {
+            bookmark.icon = Some(rasterized);
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 10 10\"><rect width=\"10\" height=\"10\" fill=\"#c00\"/></svg>";
+
+    #[test]
+    fn rasterizes_base64_and_text_svg_data_urls() {
+        let png = data_url::encode("image/png", &rasterize(SVG.as_bytes(), 16).unwrap().data);
+        let mut base64 = Bookmark::new("base64", "https://a.example/");
+        base64.icon = Some(data_url::encode("image/svg+xml", SVG.as_bytes()));
+        let mut text = Bookmark::new("text", "https://b.example/");
+        text.icon = Some(format!("data:image/svg+xml;charset=utf-8,{}", SVG.replace('<', "%3C").replace('>', "%3E").replace('#', "%23")));
+        let mut bitmap = Bookmark::new("png", "https://c.example/");
+        bitmap.icon = Some(png.clone());
+        let mut bookmarks = vec![&mut base64, &mut text, &mut bitmap];
+
+        assert_eq!(rasterize_bookmarks(&mut bookmarks, 16), 2);
+        assert_eq!(base64.icon.as_deref(), Some(png.as_str()));
+        assert_eq!(text.icon.as_deref(), Some(png.as_str()));
+        assert_eq!(bitmap.icon.as_deref(), Some(png.as_str()));
+    }
+}
@@ -0,0 +1,101 @@
+//! 测试用的本地 HTTP 服务
+//!
+//! 按顺序为每个连接返回一条预设的响应，并记录收到的请求头
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 预设的响应
+pub struct Response {
+    status: &'static str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: &'static str) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// 16x16 的 PNG 图标
+    pub fn png() -> Self {
+        Self::new("200 OK").header("Content-Type", "image/png").body(png())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", self.status, self.body.len());
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+/// 测试用的 PNG 数据
+pub fn png() -> Vec<u8> {
+    let image = image::RgbaImage::from_fn(16, 16, |x, _| image::Rgba([(x * 16) as u8, 80, 160, 255]));
+    let mut bytes = Vec::new();
+    image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+    bytes
+}
+
+/// 正在运行的服务
+pub struct TestServer {
+    /// 服务地址，如 `http://127.0.0.1:12345/favicon.ico`
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    /// 启动服务，依次用 `responses` 回应每个连接，用完后不再接受连接
+    pub async fn start(responses: Vec<Response>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/favicon.ico", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                recorded.lock().unwrap().push(String::from_utf8_lossy(&request).into_owned());
+                let _ = socket.write_all(&response.to_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// 已收到的请求（请求行和请求头）
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// 不使用系统代理的客户端，确保请求直接发往本地服务
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder().no_proxy().build().unwrap()
+}
@@ -1,8 +1,12 @@
 //! Favicon Buddy 库
 //!
 //! 这个库提供了处理书签HTML文件并添加favicon的功能
+//!
+//! 书签树模型位于 [`bookmarks`] 模块，可以直接读写和修改书签，
+//! 再通过 [`favicon::add_favicons`] 为其添加favicon
 
 pub mod ui;
+pub mod bookmarks;
 pub mod favicon;
 pub mod utils;
 pub mod errors;
@@ -12,8 +12,8 @@ fn main() -> eframe::Result<()> {
         eprintln!("初始化国际化系统失败: {}", e);
     }
 
-    // 加载配置
-    let config = AppConfig::load();
+    // 加载配置，配置文件损坏时的处理结果在设置语言后写入日志
+    let (config, recovery) = AppConfig::load_with_recovery();
 
     // 优先使用配置中的语言设置
     if let Err(e) = i18n::set_locale(&config.language.language) {
@@ -38,6 +38,6 @@ fn main() -> eframe::Result<()> {
     }
 
     // 运行应用
-    ui::run_app()
+    ui::run_app(config, recovery.map(|recovery| recovery.message()))
 }
 
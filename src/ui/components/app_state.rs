@@ -7,6 +7,7 @@ use tokio::runtime::Runtime;
 use eframe::{egui, App};
 
 use crate::config::AppConfig;
+use crate::config::favicon_service::ServiceKind;
 use super::settings::SettingsTab;
 use super::file_selector::FileSelector;
 use super::progress::ProgressBar;
@@ -16,6 +17,7 @@ use crate::ui::fonts::load_system_fonts;
 /// 应用状态结构体
 pub struct AppState {
     pub input_path: Option<String>,
+    pub keep_input_format: bool, // 是否按输入格式输出（否则输出 Netscape HTML）
     pub log: Arc<Mutex<String>>,
     pub processing: Arc<AtomicBool>,
     pub abort_flag: Arc<AtomicBool>,
@@ -24,25 +26,29 @@ pub struct AppState {
     pub config: AppConfig, // 应用配置
     pub new_service_name: String, // 新服务名称（用于UI输入）
     pub new_service_url: String, // 新服务URL模板（用于UI输入）
+    pub new_service_kind: ServiceKind, // 新服务类型（用于UI输入）
     pub show_settings_dialog: bool, // 是否显示设置对话框
     pub current_settings_tab: SettingsTab, // 当前选中的设置选项卡
     pub current_locale: String, // 当前语言
     pub available_locales: Vec<String>, // 可用语言列表
 }
 
-impl Default for AppState {
-    fn default() -> Self {
+impl AppState {
+    /// 使用启动时加载的配置创建应用状态
+    pub fn new(config: AppConfig) -> Self {
         Self {
             input_path: None,
+            keep_input_format: false,
             log: Arc::new(Mutex::new(String::new())),
             processing: Arc::new(AtomicBool::new(false)),
             abort_flag: Arc::new(AtomicBool::new(false)),
             progress: Arc::new(Mutex::new((0, 0))),
             runtime: Runtime::new()
                 .expect("Failed to create Tokio runtime"),
-            config: AppConfig::load(),
+            config,
             new_service_name: String::new(),
             new_service_url: String::new(),
+            new_service_kind: ServiceKind::Template,
             show_settings_dialog: false,
             current_settings_tab: SettingsTab::Services,
             current_locale: crate::i18n::get_locale(),
@@ -22,7 +22,12 @@ impl FileSelector {
             let select_button = create_styled_button(&select_text, select_enabled);
             if ui.add_enabled(select_enabled, select_button).clicked() {
                 if let Some(path) = FileDialog::new()
-                    .add_filter("HTML", &["html"])
+                    .add_filter("HTML", &["html", "htm"])
+                    .add_filter("JSON", &["json"])
+                    .add_filter("SQLite", &["sqlite"])
+                    .add_filter("Firefox jsonlz4", &["jsonlz4"])
+                    .add_filter("Safari plist", &["plist"])
+                    .add_filter(crate::i18n::get_message("all_files", None), &["*"])
                     .pick_file() {
                     app.input_path = Some(path.display().to_string());
                 }
@@ -39,11 +39,7 @@ impl LogViewer {
                     let total_lines = lines.len();
                     
                     // 只显示最后1000行日志以提高性能
-                    let start_idx = if total_lines > 1000 {
-                        total_lines - 1000
-                    } else {
-                        0
-                    };
+                    let start_idx = total_lines.saturating_sub(1000);
                     
                     for line in lines.iter().skip(start_idx) {
                         if line.contains("Completed:") && line.contains("Failed:") && line.contains("Total:") {
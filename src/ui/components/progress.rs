@@ -2,9 +2,12 @@
 //!
 //! 提供进度条和控制按钮的功能
 
+use std::path::Path;
 use std::sync::atomic::Ordering;
 use eframe::egui;
 
+use crate::bookmarks::BookmarkFormat;
+use crate::config::{IconPolicy, IconSize};
 use crate::ui::styles::create_styled_button;
 use super::app_state::AppState;
 
@@ -23,7 +26,10 @@ impl ProgressBar {
             if ui.add_enabled(start_enabled, start_button).clicked() {
                 if let Some(input) = &app.input_path {
                     let input = input.clone();
-                    let output = crate::utils::generate_output_filename(&input);
+                    // 输出格式：默认为 Netscape HTML，勾选"保持原始格式"时与输入格式一致
+                    let input_format = BookmarkFormat::detect(Path::new(&input)).unwrap_or(BookmarkFormat::Netscape);
+                    let output_format = if app.keep_input_format && input_format.is_writable() { input_format } else { BookmarkFormat::Netscape };
+                    let output = crate::bookmarks::output_filename(&input, input_format, output_format);
                     app.processing.store(true, Ordering::Relaxed);
                     app.abort_flag.store(false, Ordering::Relaxed);
                     
@@ -32,12 +38,13 @@ impl ProgressBar {
                     let abort_flag = app.abort_flag.clone();
                     let progress = app.progress.clone();
                     let processing = app.processing.clone();
+                    let config = app.config.clone();
                     
                     // 在新线程中执行异步任务
                     std::thread::spawn(move || {
                         let rt = tokio::runtime::Runtime::new().unwrap();
                         rt.block_on(async {
-                            if let Err(e) = crate::favicon::process_bookmarks(&input, &output, log.clone(), abort_flag.clone(), progress.clone()).await {
+                            if let Err(e) = crate::favicon::process_bookmarks(&input, &output, output_format, &config, log.clone(), abort_flag.clone(), progress.clone()).await {
                                 if let Ok(mut log_lock) = log.lock() {
                                     use std::collections::HashMap;
                                     let mut args = HashMap::new();
@@ -52,6 +59,51 @@ impl ProgressBar {
                 }
             }
 
+            // 保持原始格式
+            let keep_format_text = crate::i18n::get_message("keep_input_format", None);
+            let keep_format_enabled = !app.processing.load(Ordering::Relaxed);
+            ui.add_enabled(keep_format_enabled, egui::Checkbox::new(&mut app.keep_input_format, keep_format_text));
+
+            // 已有图标处理策略
+            ui.add_enabled_ui(keep_format_enabled, |ui| {
+                let current = app.config.icon_policy;
+                egui::ComboBox::from_id_salt("icon_policy")
+                    .selected_text(crate::i18n::get_message(current.message_key(), None))
+                    .show_ui(ui, |ui| {
+                        for policy in IconPolicy::ALL {
+                            ui.selectable_value(&mut app.config.icon_policy, policy, crate::i18n::get_message(policy.message_key(), None));
+                        }
+                    })
+                    .response
+                    .on_hover_text(crate::i18n::get_message("icon_policy", None));
+
+                // 图标尺寸
+                let current_size = app.config.icon_size;
+                egui::ComboBox::from_id_salt("icon_size")
+                    .selected_text(crate::i18n::get_message(current_size.message_key(), None))
+                    .show_ui(ui, |ui| {
+                        for size in IconSize::ALL {
+                            ui.selectable_value(&mut app.config.icon_size, size, crate::i18n::get_message(size.message_key(), None));
+                        }
+                    })
+                    .response
+                    .on_hover_text(crate::i18n::get_message("icon_size", None));
+
+                // 刷新缓存图标
+                let refresh_changed = ui.checkbox(&mut app.config.refresh_cached_icons, crate::i18n::get_message("refresh_cached_icons", None))
+                    .on_hover_text(crate::i18n::get_message("refresh_cached_icons_hint", None))
+                    .changed();
+                if app.config.icon_policy != current || app.config.icon_size != current_size || refresh_changed {
+                    if let Err(e) = app.config.save() {
+                        let mut log_lock = app.log.lock().unwrap();
+                        let mut args = std::collections::HashMap::new();
+                        args.insert("error".to_string(), e.to_string());
+                        let error_msg = crate::i18n::get_message("config_error", Some(args));
+                        log_lock.push_str(&format!("\n{}\n", error_msg));
+                    }
+                }
+            });
+
             // 停止处理按钮
             let stop_text = crate::i18n::get_message("stop_processing", None);
             let stop_enabled = app.processing.load(Ordering::Relaxed);
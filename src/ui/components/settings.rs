@@ -7,11 +7,13 @@ use super::app_state::AppState;
 // sub-modules moved into `settings/` folder
 #[path = "settings/services.rs"] mod services;
 #[path = "settings/language.rs"] mod language;
+#[path = "settings/network.rs"] mod network;
 #[path = "settings/import_export.rs"] mod import_export;
 #[path = "settings/about.rs"] mod about;
 
 use services::render as render_services_tab;
 use language::render as render_language_tab;
+use network::render as render_network_tab;
 use import_export::render as render_import_export_tab;
 use about::render as render_about_tab;
 
@@ -20,6 +22,7 @@ use about::render as render_about_tab;
 pub enum SettingsTab {
     Services,
     Language,
+    Network,
     ImportExport,
     About,
 }
@@ -36,6 +39,10 @@ pub fn render_settings(app: &mut AppState, ui: &mut egui::Ui, ctx: &egui::Contex
             crate::i18n::get_message("language", None)).clicked() {
             app.current_settings_tab = SettingsTab::Language;
         }
+        if ui.selectable_label(app.current_settings_tab == SettingsTab::Network,
+            crate::i18n::get_message("network", None)).clicked() {
+            app.current_settings_tab = SettingsTab::Network;
+        }
         if ui.selectable_label(app.current_settings_tab == SettingsTab::ImportExport,
             crate::i18n::get_message("import_export", None)).clicked() {
             app.current_settings_tab = SettingsTab::ImportExport;
@@ -57,6 +64,7 @@ pub fn render_settings(app: &mut AppState, ui: &mut egui::Ui, ctx: &egui::Contex
         SettingsTab::ImportExport => render_import_export_tab(app, ui),
         SettingsTab::Services => render_services_tab(app, ui),
         SettingsTab::Language => render_language_tab(app, ui, ctx),
+        SettingsTab::Network => render_network_tab(app, ui),
         SettingsTab::About => render_about_tab(ui),
     }
 }
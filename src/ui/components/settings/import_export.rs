@@ -4,7 +4,7 @@ use eframe::egui;
 use rfd::FileDialog;
 use chrono;
 use crate::ui::styles::create_styled_button;
-use crate::config::{AppConfig, ConfigImportExport};
+use crate::config::{AppConfig, CacheBackend, ConfigImportExport};
 use super::super::app_state::AppState;
 
 /// 渲染导入导出选项卡
@@ -24,9 +24,9 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             let mut log_lock = app.log.lock().unwrap();
                             let success_msg = format!("{}\n{}: {}", result.message, crate::i18n::get_message("export_path", None), filepath.display());
                             ui.add(egui::Label::new(egui::RichText::new(&success_msg).color(egui::Color32::GREEN)));
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                             log_lock.push_str(&success_msg);
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                         }
                         Err(e) => {
                             let mut log_lock = app.log.lock().unwrap();
@@ -34,9 +34,9 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             args.insert("error".to_string(), e.to_string());
                             let error_msg = crate::i18n::get_message("config_export_error", Some(args));
                             ui.add(egui::Label::new(egui::RichText::new(&error_msg).color(egui::Color32::RED)));
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                             log_lock.push_str(&error_msg);
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                         }
                     }
                 }
@@ -83,9 +83,9 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             let mut log_lock = app.log.lock().unwrap();
                             let success_msg = format!("{}\n{}: {}", result.message, crate::i18n::get_message("export_path", None), filepath.display());
                             ui.add(egui::Label::new(egui::RichText::new(&success_msg).color(egui::Color32::GREEN)));
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                             log_lock.push_str(&success_msg);
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                         }
                         Err(e) => {
                             let mut log_lock = app.log.lock().unwrap();
@@ -93,9 +93,9 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             args.insert("error".to_string(), e.to_string());
                             let error_msg = crate::i18n::get_message("cache_export_error", Some(args));
                             ui.add(egui::Label::new(egui::RichText::new(&error_msg).color(egui::Color32::RED)));
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                             log_lock.push_str(&error_msg);
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                         }
                     }
                 }
@@ -118,11 +118,11 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             match app.config.import_cache(path.as_path()) {
                                 Ok(result) => {
                                     let mut log_lock = app.log.lock().unwrap();
-                                    let success_msg = format!("{}", result.message);
+                                    let success_msg = result.message.to_string();
                                     ui.add(egui::Label::new(egui::RichText::new(&success_msg).color(egui::Color32::GREEN)));
-                                    log_lock.push_str("\n");
+                                    log_lock.push('\n');
                                     log_lock.push_str(&success_msg);
-                                    log_lock.push_str("\n");
+                                    log_lock.push('\n');
                                 }
                                 Err(e) => {
                                     let mut log_lock = app.log.lock().unwrap();
@@ -130,9 +130,9 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                                     args.insert("error".to_string(), e.to_string());
                                     let error_msg = crate::i18n::get_message("cache_import_error", Some(args));
                                     ui.add(egui::Label::new(egui::RichText::new(&error_msg).color(egui::Color32::RED)));
-                                    log_lock.push_str("\n");
+                                    log_lock.push('\n');
                                     log_lock.push_str(&error_msg);
-                                    log_lock.push_str("\n");
+                                    log_lock.push('\n');
                                 }
                             }
                         },
@@ -142,13 +142,94 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                             args.insert("error".to_string(), e.to_string());
                             let error_msg = crate::i18n::get_message("cache_import_error", Some(args));
                             ui.add(egui::Label::new(egui::RichText::new(&error_msg).color(egui::Color32::RED)));
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
                             log_lock.push_str(&error_msg);
-                            log_lock.push_str("\n");
+                            log_lock.push('\n');
+                        }
+                    }
+                }
+            }
+
+            let seed_text = crate::i18n::get_message("seed_cache", None);
+            let seed_button = create_styled_button(&seed_text, true);
+            if ui.add(seed_button).clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("HTML", &["html", "htm"])
+                    .add_filter("JSON", &["json"])
+                    .add_filter("SQLite", &["sqlite"])
+                    .add_filter("Firefox jsonlz4", &["jsonlz4"])
+                    .add_filter("Safari plist", &["plist"])
+                    .add_filter(crate::i18n::get_message("all_files", None), &["*"])
+                    .pick_file() {
+                    match app.config.seed_cache(path.as_path()) {
+                        Ok(result) => {
+                            let mut log_lock = app.log.lock().unwrap();
+                            let success_msg = result.message.to_string();
+                            ui.add(egui::Label::new(egui::RichText::new(&success_msg).color(egui::Color32::GREEN)));
+                            log_lock.push('\n');
+                            log_lock.push_str(&success_msg);
+                            log_lock.push('\n');
+                        }
+                        Err(e) => {
+                            let mut log_lock = app.log.lock().unwrap();
+                            let mut args = std::collections::HashMap::new();
+                            args.insert("error".to_string(), e.to_string());
+                            let error_msg = crate::i18n::get_message("cache_seed_error", Some(args));
+                            ui.add(egui::Label::new(egui::RichText::new(&error_msg).color(egui::Color32::RED)));
+                            log_lock.push('\n');
+                            log_lock.push_str(&error_msg);
+                            log_lock.push('\n');
                         }
                     }
                 }
             }
         });
+
+        ui.separator();
+
+        // 缓存设置
+        let cache = &mut app.config.cache;
+        let mut changed = false;
+        egui::Grid::new("cache_settings_grid")
+            .num_columns(2)
+            .spacing([10.0, 6.0])
+            .show(ui, |ui| {
+                ui.label(crate::i18n::get_message("cache_backend", None));
+                let current_backend = cache.backend;
+                egui::ComboBox::from_id_salt("cache_backend")
+                    .selected_text(crate::i18n::get_message(current_backend.message_key(), None))
+                    .show_ui(ui, |ui| {
+                        for backend in CacheBackend::ALL {
+                            ui.selectable_value(&mut cache.backend, backend, crate::i18n::get_message(backend.message_key(), None));
+                        }
+                    })
+                    .response
+                    .on_hover_text(crate::i18n::get_message("cache_backend_hint", None));
+                changed |= cache.backend != current_backend;
+                ui.end_row();
+
+                let days = format!(" {}", crate::i18n::get_message("days", None));
+                ui.label(crate::i18n::get_message("positive_ttl", None));
+                changed |= ui.add(egui::DragValue::new(&mut cache.positive_ttl_days).range(0..=3650).suffix(&days))
+                    .on_hover_text(crate::i18n::get_message("ttl_hint", None))
+                    .changed();
+                ui.end_row();
+
+                ui.label(crate::i18n::get_message("negative_ttl", None));
+                changed |= ui.add(egui::DragValue::new(&mut cache.negative_ttl_days).range(0..=3650).suffix(&days))
+                    .on_hover_text(crate::i18n::get_message("ttl_hint", None))
+                    .changed();
+                ui.end_row();
+            });
+
+        if changed {
+            if let Err(e) = app.config.save() {
+                let mut log_lock = app.log.lock().unwrap();
+                let mut args = std::collections::HashMap::new();
+                args.insert("error".to_string(), e.to_string());
+                let error_msg = crate::i18n::get_message("config_error", Some(args));
+                log_lock.push_str(&format!("\n{}\n", error_msg));
+            }
+        }
     });
 }
@@ -0,0 +1,70 @@
+//! 网络选项卡
+
+use eframe::egui;
+use rfd::FileDialog;
+use super::super::app_state::AppState;
+
+/// 渲染网络选项卡
+pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
+    let network = &mut app.config.network;
+    let mut changed = false;
+
+    egui::Grid::new("network_settings_grid")
+        .num_columns(2)
+        .spacing([10.0, 6.0])
+        .show(ui, |ui| {
+            ui.label(crate::i18n::get_message("connect_timeout", None));
+            changed |= ui.add(egui::DragValue::new(&mut network.connect_timeout_secs).range(1..=120).suffix(" s")).changed();
+            ui.end_row();
+
+            ui.label(crate::i18n::get_message("read_timeout", None));
+            changed |= ui.add(egui::DragValue::new(&mut network.read_timeout_secs).range(1..=300).suffix(" s")).changed();
+            ui.end_row();
+
+            ui.label(crate::i18n::get_message("max_retries", None));
+            changed |= ui.add(egui::DragValue::new(&mut network.max_retries).range(0..=10)).changed();
+            ui.end_row();
+
+            ui.label(crate::i18n::get_message("user_agent", None));
+            changed |= ui.add_sized([300.0, 20.0], egui::TextEdit::singleline(&mut network.user_agent)).changed();
+            ui.end_row();
+
+            ui.label(crate::i18n::get_message("proxy", None));
+            let mut proxy = network.proxy.clone().unwrap_or_default();
+            if ui.add_sized([300.0, 20.0], egui::TextEdit::singleline(&mut proxy).hint_text("socks5h://127.0.0.1:1080")).changed() {
+                network.proxy = Some(proxy).filter(|p| !p.trim().is_empty());
+                changed = true;
+            }
+            ui.end_row();
+
+            ui.label(crate::i18n::get_message("ca_bundle", None));
+            ui.horizontal(|ui| {
+                let mut ca_bundle = network.ca_bundle.clone().unwrap_or_default();
+                if ui.add_sized([220.0, 20.0], egui::TextEdit::singleline(&mut ca_bundle)).changed() {
+                    network.ca_bundle = Some(ca_bundle).filter(|p| !p.trim().is_empty());
+                    changed = true;
+                }
+                if ui.button("...").clicked() {
+                    if let Some(path) = FileDialog::new().add_filter("PEM", &["pem", "crt", "cer"]).pick_file() {
+                        network.ca_bundle = Some(path.display().to_string());
+                        changed = true;
+                    }
+                }
+            });
+            ui.end_row();
+
+            ui.label("");
+            changed |= ui.checkbox(&mut network.accept_invalid_certs, crate::i18n::get_message("accept_invalid_certs", None)).changed();
+            ui.end_row();
+        });
+
+    if changed {
+        if let Err(e) = app.config.save() {
+            let mut log_lock = app.log.lock().unwrap();
+            let mut args = std::collections::HashMap::new();
+            args.insert("error".to_string(), e.to_string());
+            let error_msg = crate::i18n::get_message("config_error", Some(args));
+            log_lock.push_str(&format!("\n{}\n", error_msg));
+        }
+    }
+}
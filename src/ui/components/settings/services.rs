@@ -1,68 +1,84 @@
 //! 服务选项卡（Favicon 服务配置）
 
 use eframe::egui;
+use crate::config::favicon_service::ServiceKind;
 use crate::ui::styles::create_styled_button;
 use super::super::app_state::AppState;
 
+/// 服务列表中的操作
+enum ServiceAction {
+    Toggle(usize),
+    MoveUp(usize),
+    MoveDown(usize),
+    Remove(usize),
+    Confirm(usize),
+}
+
 /// 渲染服务选项卡
 pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
-    // 显示当前服务
-    let current_service = &app.config.favicon_service.services[app.config.favicon_service.current_service_index];
+    // 显示获取顺序
+    let chain: Vec<&str> = app.config.favicon_service.chain().map(|service| service.name.as_str()).collect();
     let mut args = std::collections::HashMap::new();
-    args.insert("name".to_string(), current_service.name.clone());
-    let current_service_text = crate::i18n::get_message("current_service", Some(args));
-    ui.label(&current_service_text);
+    args.insert("names".to_string(), chain.join(" → "));
+    let service_chain_text = crate::i18n::get_message("service_chain", Some(args));
+    ui.label(&service_chain_text);
     ui.separator();
 
     // 服务列表
     let available_services = crate::i18n::get_message("available_services", None);
     ui.label(&available_services);
+    let mut action = None;
     egui::Grid::new("favicon_services_grid")
-        .num_columns(3)
+        .num_columns(4)
         .spacing([10.0, 6.0])
         .striped(true)
         .show(ui, |ui| {
             let service_name = crate::i18n::get_message("service_name", None);
             let url_template = crate::i18n::get_message("url_template", None);
             let actions = crate::i18n::get_message("actions", None);
+            ui.label("");
             ui.label(&service_name);
             ui.label(&url_template);
             ui.label(&actions);
             ui.end_row();
 
-            let services: Vec<_> = app.config.favicon_service.services.iter().enumerate()
-                .map(|(i, s)| (i, s.name.clone(), s.url_template.clone(), s.is_default, i == app.config.favicon_service.current_service_index))
-                .collect();
-
-            for (i, name, url_template, is_default, is_current) in services {
-                ui.label(&name);
-                ui.label(&url_template);
+            let count = app.config.favicon_service.services.len();
+            for (i, service) in app.config.favicon_service.services.iter().enumerate() {
+                let mut enabled = service.enabled;
+                // 导入的外部命令服务确认前不能启用
+                if ui.add_enabled(!service.needs_confirmation, egui::Checkbox::without_text(&mut enabled))
+                    .on_hover_text(crate::i18n::get_message("enable_service", None)).changed() {
+                    action = Some(ServiceAction::Toggle(i));
+                }
+                ui.label(&service.name);
+                match service.kind {
+                    ServiceKind::Direct => ui.label(crate::i18n::get_message("direct_service_hint", None)),
+                    _ if service.needs_confirmation => ui.label(egui::RichText::new(&service.url_template).color(egui::Color32::from_rgb(200, 120, 0)))
+                        .on_hover_text(crate::i18n::get_message("command_needs_confirmation", None)),
+                    _ => ui.label(&service.url_template),
+                }
+                .on_hover_text(crate::i18n::get_message(service.kind.message_key(), None));
                 ui.horizontal(|ui| {
-                    let use_text = crate::i18n::get_message("use_service", None);
-                    let use_button = create_styled_button(&use_text, !is_current);
-                    if ui.add_enabled(!is_current, use_button).clicked() {
-                        app.config.favicon_service.current_service_index = i;
-                        if let Err(e) = app.config.save() {
-                            let mut log_lock = app.log.lock().unwrap();
-                            let mut args = std::collections::HashMap::new();
-                            args.insert("error".to_string(), e.to_string());
-                            let error_msg = crate::i18n::get_message("config_error", Some(args));
-                            log_lock.push_str(&format!("\n{}\n", error_msg));
+                    if service.needs_confirmation {
+                        let confirm_text = crate::i18n::get_message("confirm_command", None);
+                        if ui.add(create_styled_button(&confirm_text, true))
+                            .on_hover_text(crate::i18n::get_message("command_needs_confirmation", None)).clicked() {
+                            action = Some(ServiceAction::Confirm(i));
                         }
                     }
+                    if ui.add_enabled(i > 0, egui::Button::new("↑")).on_hover_text(crate::i18n::get_message("move_up", None)).clicked() {
+                        action = Some(ServiceAction::MoveUp(i));
+                    }
+                    if ui.add_enabled(i + 1 < count, egui::Button::new("↓")).on_hover_text(crate::i18n::get_message("move_down", None)).clicked() {
+                        action = Some(ServiceAction::MoveDown(i));
+                    }
 
-                    if !is_default {
+                    // 内置的直接获取服务只能停用，删除后加载配置时会被重新添加
+                    if !service.is_default && service.kind != ServiceKind::Direct {
                         let delete_text = crate::i18n::get_message("remove_service", None);
                         let delete_button = create_styled_button(&delete_text, true);
                         if ui.add(delete_button).clicked() {
-                            app.config.favicon_service.services.remove(i);
-                            if let Err(e) = app.config.save() {
-                                let mut log_lock = app.log.lock().unwrap();
-                                let mut args = std::collections::HashMap::new();
-                                args.insert("error".to_string(), e.to_string());
-                                let error_msg = crate::i18n::get_message("config_error", Some(args));
-                                log_lock.push_str(&format!("\n{}\n", error_msg));
-                            }
+                            action = Some(ServiceAction::Remove(i));
                         }
                     }
                 });
@@ -70,6 +86,100 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
             }
         });
 
+    if let Some(action) = action {
+        let services = &mut app.config.favicon_service;
+        match action {
+            ServiceAction::Toggle(i) => services.services[i].enabled = !services.services[i].enabled,
+            ServiceAction::MoveUp(i) => services.swap(i - 1, i),
+            ServiceAction::MoveDown(i) => services.swap(i, i + 1),
+            ServiceAction::Remove(i) => {
+                services.services.remove(i);
+            }
+            ServiceAction::Confirm(i) => {
+                services.services[i].needs_confirmation = false;
+                services.services[i].enabled = true;
+            }
+        }
+        if let Err(e) = app.config.save() {
+            let mut log_lock = app.log.lock().unwrap();
+            let mut args = std::collections::HashMap::new();
+            args.insert("error".to_string(), e.to_string());
+            let error_msg = crate::i18n::get_message("config_error", Some(args));
+            log_lock.push_str(&format!("\n{}\n", error_msg));
+        }
+    }
+
+    ui.separator();
+
+    // 各模板服务的占位图标哈希，每行一个
+    let placeholder_hashes_label = crate::i18n::get_message("placeholder_hashes", None);
+    ui.label(&placeholder_hashes_label);
+    let mut changed = false;
+    for (i, service) in app.config.favicon_service.services.iter_mut().enumerate() {
+        if service.kind != ServiceKind::Template {
+            continue;
+        }
+        egui::CollapsingHeader::new(format!("{} ({})", service.name, service.placeholder_hashes.len()))
+            .id_salt(("placeholder_hashes", i))
+            .show(ui, |ui| {
+                let probe_text = crate::i18n::get_message("probe_placeholder", None);
+                changed |= ui.checkbox(&mut service.probe_placeholder, probe_text).changed();
+                // 编辑中的文本保存在 egui 内存中，失去焦点时再写回配置
+                let id = ui.id().with(("placeholder_hashes_text", i));
+                let mut text = ui.data_mut(|data| data.get_temp::<String>(id)).unwrap_or_else(|| service.placeholder_hashes.join("\n"));
+                let response = ui.add(egui::TextEdit::multiline(&mut text).desired_rows(3).desired_width(400.0).font(egui::TextStyle::Monospace));
+                if response.lost_focus() {
+                    let hashes: Vec<String> = text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect();
+                    if hashes != service.placeholder_hashes {
+                        service.placeholder_hashes = hashes;
+                        changed = true;
+                    }
+                    ui.data_mut(|data| data.remove::<String>(id));
+                } else if response.has_focus() {
+                    ui.data_mut(|data| data.insert_temp(id, text));
+                }
+                ui.label(egui::RichText::new(crate::i18n::get_message("placeholder_hashes_hint", None)).color(egui::Color32::GRAY));
+            });
+    }
+    if changed {
+        if let Err(e) = app.config.save() {
+            let mut log_lock = app.log.lock().unwrap();
+            let mut args = std::collections::HashMap::new();
+            args.insert("error".to_string(), e.to_string());
+            let error_msg = crate::i18n::get_message("config_error", Some(args));
+            log_lock.push_str(&format!("\n{}\n", error_msg));
+        }
+    }
+
+    ui.separator();
+
+    // 并发请求数
+    ui.horizontal(|ui| {
+        let concurrency_label = crate::i18n::get_message("concurrency", None);
+        ui.label(&concurrency_label);
+        if ui.add(egui::DragValue::new(&mut app.config.concurrency).range(1..=32)).changed() {
+            if let Err(e) = app.config.save() {
+                let mut log_lock = app.log.lock().unwrap();
+                let mut args = std::collections::HashMap::new();
+                args.insert("error".to_string(), e.to_string());
+                let error_msg = crate::i18n::get_message("config_error", Some(args));
+                log_lock.push_str(&format!("\n{}\n", error_msg));
+            }
+        }
+    });
+
+    // 生成字母图标
+    let generate_text = crate::i18n::get_message("generate_fallback_icons", None);
+    if ui.checkbox(&mut app.config.generate_fallback_icons, generate_text).changed() {
+        if let Err(e) = app.config.save() {
+            let mut log_lock = app.log.lock().unwrap();
+            let mut args = std::collections::HashMap::new();
+            args.insert("error".to_string(), e.to_string());
+            let error_msg = crate::i18n::get_message("config_error", Some(args));
+            log_lock.push_str(&format!("\n{}\n", error_msg));
+        }
+    }
+
     ui.separator();
 
     // 添加新服务
@@ -80,6 +190,17 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
         ui.label(&name_label);
         ui.text_edit_singleline(&mut app.new_service_name);
     });
+    ui.horizontal(|ui| {
+        let service_kind_label = crate::i18n::get_message("service_kind", None);
+        ui.label(&service_kind_label);
+        egui::ComboBox::from_id_salt("new_service_kind")
+            .selected_text(crate::i18n::get_message(app.new_service_kind.message_key(), None))
+            .show_ui(ui, |ui| {
+                for kind in ServiceKind::ADDABLE {
+                    ui.selectable_value(&mut app.new_service_kind, kind, crate::i18n::get_message(kind.message_key(), None));
+                }
+            });
+    });
     ui.horizontal(|ui| {
         let url_template_label = crate::i18n::get_message("url_template_label", None);
         ui.label(&url_template_label);
@@ -94,6 +215,11 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
                     name: app.new_service_name.clone(),
                     url_template: app.new_service_url.clone(),
                     is_default: false,
+                    kind: app.new_service_kind,
+                    enabled: true,
+                    placeholder_hashes: Vec::new(),
+                    probe_placeholder: true,
+                    needs_confirmation: false,
                 });
                 if let Err(e) = app.config.save() {
                     let mut log_lock = app.log.lock().unwrap();
@@ -112,7 +238,7 @@ pub fn render(app: &mut AppState, ui: &mut egui::Ui) {
             }
         }
         ui.add_space(10.0);
-        let domain_placeholder = crate::i18n::get_message("domain_placeholder_hint", None);
-        ui.label(egui::RichText::new(&domain_placeholder).color(egui::Color32::GRAY));
+        let hint = crate::i18n::get_message(app.new_service_kind.hint_key(), None);
+        ui.label(egui::RichText::new(&hint).color(egui::Color32::GRAY));
     });
 }
@@ -25,9 +25,7 @@ pub fn load_system_fonts(ctx: &egui::Context) {
     }
 
     // 确保 Proportional 字体系列存在
-    if !fonts.families.contains_key(&FontFamily::Proportional) {
-        fonts.families.insert(FontFamily::Proportional, Vec::new());
-    }
+    fonts.families.entry(FontFamily::Proportional).or_default();
 
     // 重新组织字体优先级 - 确保中文字体在前面
     #[cfg(target_os = "macos")]
@@ -10,6 +10,8 @@ mod embedded_fonts;
 use eframe::egui;
 use egui::viewport::IconData;
 
+use crate::config::AppConfig;
+
 // 在编译期嵌入 icon.png，便于可执行文件单独分发
 const EMBED_ICON_PNG: &[u8] = include_bytes!("../../assets/icon.png");
 
@@ -19,7 +21,7 @@ pub use fonts::{load_system_fonts, get_system_font_paths};
 pub use styles::create_styled_button;
 
 /// 初始化并运行应用程序
-pub fn run_app() -> eframe::Result<()> {
+pub fn run_app(config: AppConfig, startup_log: Option<String>) -> eframe::Result<()> {
     let mut options = eframe::NativeOptions::default();
 
     // 为 macOS 添加特定的渲染选项
@@ -45,6 +47,12 @@ pub fn run_app() -> eframe::Result<()> {
     eframe::run_native(
         &crate::i18n::get_message("app_title", None),
         options,
-        Box::new(|_cc| Ok::<Box<dyn eframe::App>, Box<dyn std::error::Error + Send + Sync>>(Box::new(AppState::default()))),
+        Box::new(|_cc| {
+            let app = AppState::new(config);
+            if let (Some(message), Ok(mut log_lock)) = (startup_log, app.log.lock()) {
+                log_lock.push_str(&format!("{}\n", message));
+            }
+            Ok::<Box<dyn eframe::App>, Box<dyn std::error::Error + Send + Sync>>(Box::new(app))
+        }),
     )
 }
\ No newline at end of file
@@ -5,7 +5,7 @@
 use egui;
 
 /// 辅助函数：创建统一样式的按钮
-pub fn create_styled_button(text: &str, enabled: bool) -> egui::Button {
+pub fn create_styled_button(text: &str, enabled: bool) -> egui::Button<'_> {
     let mut button = egui::Button::new(text);
     if enabled {
         button = button.fill(egui::Color32::from_rgb(210, 210, 210)); // 启用状态为灰色 #d2d2d2
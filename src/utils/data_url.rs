@@ -0,0 +1,43 @@
+//! Data URL 工具模块
+//!
+//! 提供 base64 data URL 的编码和解码，以及文本 data URL 的解码
+
+use base64::Engine;
+
+/// 将图片数据编码为 base64 data URL
+pub fn encode(mime: &str, bytes: &[u8]) -> String {
+    let b64 = base64::engine::general_purpose::STANDARD.encode(bytes);
+    from_base64(mime, &b64)
+}
+
+/// 用已经过 base64 编码的数据组成 data URL
+pub fn from_base64(mime: &str, b64: &str) -> String {
+    format!("data:{};base64,{}", mime, b64)
+}
+
+/// 解码 base64 data URL，返回 MIME 类型和原始数据
+///
+/// 非 data URL 或非 base64 编码时返回 None
+pub fn decode(url: &str) -> Option<(String, Vec<u8>)> {
+    let rest = url.trim().strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let mime = meta.strip_suffix(";base64")?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    let mime = if mime.is_empty() { "text/plain" } else { mime };
+    Some((mime.to_string(), bytes))
+}
+
+/// 解码 data URL，除 base64 外也支持百分号编码的文本（如 `data:image/svg+xml,%3Csvg ...`）
+///
+/// 返回的 MIME 类型不含 `charset` 等参数。非 data URL 或 base64 数据无效时返回 None
+pub fn decode_any(url: &str) -> Option<(String, Vec<u8>)> {
+    let rest = url.trim().strip_prefix("data:")?;
+    let (meta, data) = rest.split_once(',')?;
+    let bytes = match meta.strip_suffix(";base64") {
+        Some(_) => base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?,
+        None => percent_encoding::percent_decode_str(data).collect(),
+    };
+    let mime = meta.split(';').next().unwrap_or_default().trim();
+    let mime = if mime.is_empty() { "text/plain" } else { mime };
+    Some((mime.to_string(), bytes))
+}
@@ -2,7 +2,11 @@
 //! 
 //! 提供文件操作相关功能
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use chrono::Local;
 
 /// 生成输出文件名
@@ -10,9 +14,16 @@ use chrono::Local;
 /// 基于输入文件名生成带有"-with-favicons"后缀和时间戳的输出文件名
 /// 格式：原文件名-with-favicons--YYYY-MM-DD-HHMMSS.扩展名
 pub fn generate_output_filename(input: &str) -> String {
+    let ext = Path::new(input).extension().and_then(|e| e.to_str()).unwrap_or("html");
+    generate_output_filename_with_extension(input, ext)
+}
+
+/// 生成指定扩展名的输出文件名
+///
+/// 命名规则与 [`generate_output_filename`] 相同，用于输出格式与输入格式不同的情况
+pub fn generate_output_filename_with_extension(input: &str, ext: &str) -> String {
     let path = Path::new(input);
     let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("html");
     let dir = path.parent().map(|p| p.to_str().unwrap_or("")).unwrap_or("");
     
     // 获取当前时间并格式化为：YYYY-MM-DD-HHMMSS
@@ -31,4 +42,154 @@ pub fn generate_output_filename(input: &str) -> String {
             format!("{}/{}-with-favicons--{}.{}", dir, stem, timestamp, ext)
         }
     }
-}
\ No newline at end of file
+}
+
+/// 在路径后追加后缀，如 `config.json` → `config.json.bak`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 文件的备份路径
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// 把内容写入同目录的临时文件并同步到磁盘，返回临时文件路径
+fn write_temp(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(tmp)
+}
+
+/// 同步文件所在的目录，确保改名本身也已写入磁盘
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// 先写入同目录的临时文件，再改名替换目标文件
+fn replace_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = write_temp(path, contents)?;
+    fs::rename(&tmp, path)?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// 原子写入文件
+///
+/// 写入过程中崩溃或断电时，目标文件保持为旧内容或新内容之一，不会只写入一半。
+/// 目标文件已存在时先改名为 `.bak` 备份，再把写好的临时文件改名为目标文件，每次写入轮换一次；
+/// 两次改名之间崩溃时目标文件暂时缺失，由 [`read_with_backup`] 从备份读取
+pub fn write_atomic(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let tmp = write_temp(path, contents.as_ref())?;
+    match fs::rename(path, backup_path(path)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::rename(&tmp, path)?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// 文件损坏时的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// 损坏的文件
+    pub path: PathBuf,
+    /// 读取或解析失败的原因
+    pub error: String,
+    /// 是否已从 `.bak` 备份恢复
+    pub restored: bool,
+}
+
+impl Recovery {
+    /// 说明发生了什么的日志消息
+    pub fn message(&self) -> String {
+        let mut args = HashMap::new();
+        args.insert("file".to_string(), self.path.display().to_string());
+        args.insert("error".to_string(), self.error.clone());
+        args.insert("backup".to_string(), backup_path(&self.path).display().to_string());
+        let key = if self.restored { "file_restored_from_backup" } else { "file_unreadable" };
+        crate::i18n::get_message(key, Some(args))
+    }
+}
+
+/// 读取并解析文件，文件无法读取或解析时改用 `.bak` 备份
+///
+/// 文件和备份都不存在时返回 None；文件不存在而备份存在时（如写入时两次改名之间崩溃）同样改用备份。
+/// 使用备份时立即用备份替换损坏的文件，避免下次写入时把损坏的内容轮换进备份；
+/// 文件和备份都不可用时返回 None，并在 [`Recovery`] 中说明原因
+pub fn read_with_backup<T, E: Display>(path: &Path, parse: impl Fn(&str) -> Result<T, E>) -> (Option<T>, Option<Recovery>) {
+    let error = match fs::read_to_string(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !backup_path(path).exists() => return (None, None),
+        Err(e) => e.to_string(),
+        Ok(content) => match parse(&content) {
+            Ok(value) => return (Some(value), None),
+            Err(e) => e.to_string(),
+        },
+    };
+    let backup = fs::read_to_string(backup_path(path)).ok()
+        .and_then(|content| parse(&content).ok().map(|value| (value, content)));
+    let restored = backup.is_some();
+    let value = backup.map(|(value, content)| {
+        let _ = replace_file(path, content.as_bytes());
+        value
+    });
+    (value, Some(Recovery { path: path.to_path_buf(), error, restored }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<u32, std::num::ParseIntError> {
+        content.parse()
+    }
+
+    #[test]
+    fn write_atomic_rotates_previous_file_into_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        write_atomic(&path, "1").unwrap();
+        assert!(!backup_path(&path).exists());
+        write_atomic(&path, "2").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2");
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "1");
+        assert!(!with_suffix(&path, ".tmp").exists());
+    }
+
+    #[test]
+    fn damaged_file_is_restored_from_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, "garbage").unwrap();
+        fs::write(backup_path(&path), "1").unwrap();
+        let (value, recovery) = read_with_backup(&path, parse);
+        assert_eq!(value, Some(1));
+        assert!(recovery.unwrap().restored);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1");
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        assert_eq!(read_with_backup(&path, parse), (None, None));
+
+        // 写入时在两次改名之间崩溃，只剩下备份
+        fs::write(backup_path(&path), "1").unwrap();
+        let (value, recovery) = read_with_backup(&path, parse);
+        assert_eq!(value, Some(1));
+        assert!(recovery.unwrap().restored);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1");
+    }
+}
@@ -8,4 +8,17 @@
 pub fn format_log_message(message: &str) -> String {
     use chrono::Local;
     format!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message)
-}
\ No newline at end of file
+}
+
+/// 格式化字节数，如 `512 B`、`1.5 KB`、`2.3 MB`
+pub fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let value = bytes as f64;
+    if value < KB {
+        format!("{} B", bytes)
+    } else if value < KB * KB {
+        format!("{:.1} KB", value / KB)
+    } else {
+        format!("{:.1} MB", value / KB / KB)
+    }
+}
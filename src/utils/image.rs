@@ -0,0 +1,111 @@
+//! 图片类型工具模块
+//!
+//! 根据文件头判断图片的 MIME 类型并校验图片数据
+
+use image::ImageError;
+use image::error::{DecodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind};
+
+/// 根据文件头判断图片类型，无法识别时返回 None
+pub fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if data.starts_with(&[0, 0, 1, 0]) {
+        Some("image/x-icon")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else if is_svg(data) {
+        Some("image/svg+xml")
+    } else {
+        None
+    }
+}
+
+/// 判断数据是否为 SVG 文本
+///
+/// 跳过 XML 声明、处理指令、注释和 DOCTYPE 后，第一个元素为 `<svg>` 时视为 SVG；
+/// 只看根元素，内嵌 SVG 的 HTML 页面不会被误判
+fn is_svg(data: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(data);
+    let mut rest = text.trim_start_matches('\u{feff}');
+    loop {
+        rest = rest.trim_start();
+        let end = if rest.starts_with("<?") {
+            rest.find("?>").map(|i| i + 2)
+        } else if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else if rest.get(..9).is_some_and(|head| head.eq_ignore_ascii_case("<!DOCTYPE")) {
+            doctype_end(rest)
+        } else {
+            let Some(tag) = rest.strip_prefix("<svg").or_else(|| rest.strip_prefix("<svg:svg")) else {
+                return false;
+            };
+            return tag.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/');
+        };
+        match end {
+            Some(end) => rest = &rest[end..],
+            None => return false,
+        }
+    }
+}
+
+/// DOCTYPE 声明的结束位置，内部子集（`[...]`）中的 `>` 不算结束
+fn doctype_end(doctype: &str) -> Option<usize> {
+    let close = doctype.find('>')?;
+    match doctype.find('[') {
+        Some(open) if open < close => {
+            let subset_end = open + doctype[open..].find(']')?;
+            Some(subset_end + doctype[subset_end..].find('>')? + 1)
+        }
+        _ => Some(close + 1),
+    }
+}
+
+/// 校验图片数据，返回根据文件头判断的 MIME 类型
+///
+/// 位图用 image 完整解码，确认不是错误页面或截断的数据；SVG 只检查是否为文本
+pub fn validate(data: &[u8]) -> Result<&'static str, ImageError> {
+    let Some(mime) = sniff_mime(data) else {
+        return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+            ImageFormatHint::Unknown,
+            UnsupportedErrorKind::Format(ImageFormatHint::Unknown),
+        )));
+    };
+    if mime == "image/svg+xml" {
+        if std::str::from_utf8(data).is_err() {
+            return Err(ImageError::Decoding(DecodingError::new(ImageFormatHint::Name("SVG".to_string()), "SVG is not valid UTF-8")));
+        }
+        return Ok(mime);
+    }
+    image::load_from_memory(data)?;
+    Ok(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_svg_after_prolog() {
+        assert!(is_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        assert!(is_svg(b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<svg>"));
+        assert!(is_svg(b"<!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\">\n<svg version=\"1.1\"></svg>"));
+        assert!(is_svg(b"<?xml version=\"1.0\"?><!DOCTYPE svg [ <!ENTITY ns \"http://www.w3.org/2000/svg\"> ]><svg xmlns=\"&ns;\"/>"));
+        let long_comment = format!("<!-- {} --><svg></svg>", "x".repeat(2000));
+        assert!(is_svg(long_comment.as_bytes()));
+    }
+
+    #[test]
+    fn rejects_html_and_other_text() {
+        assert!(!is_svg(b"<!DOCTYPE html><html><body><svg></svg></body></html>"));
+        assert!(!is_svg(b"<!-- page --><html><svg></svg></html>"));
+        assert!(!is_svg(b"<svgfoo/>"));
+        assert!(!is_svg(b"not an image"));
+        assert!(!is_svg(b"<!-- unterminated <svg>"));
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! 提供各种工具函数
 
+pub mod data_url;
 pub mod file;
 pub mod format;
+pub mod image;
 
-pub use file::generate_output_filename;
-pub use format::format_log_message;
\ No newline at end of file
+pub use file::{generate_output_filename, generate_output_filename_with_extension, read_with_backup, write_atomic, Recovery};
+pub use format::{format_log_message, format_size};
\ No newline at end of file